@@ -6,9 +6,176 @@ device-load-failed = Failed to load the device
 show-device-info = Show Device Info
 reset-control = Reset to default
 save = Save
+profile = Profile
+search-controls = Search controls
+no-matching-controls = No controls match the search
+no-controls = This device has no controls
+unsupported-control = {$kind} controls are not supported yet
+favorites = Favorites
+no-favorites = Pin controls with the star button to reach them here
+pin-control = Pin to favorites
+unpin-control = Unpin from favorites
+link-camera = Linked
+no-linked-cameras = Link another camera to keep it in step with this one
+link-in-sync = In sync
+disabled-read-only = Read-only on this device
+disabled-by-driver = Disabled by the driver
+disabled-inactive = Inactive in the current mode
+disabled-by-control = Inactive while {$control} is in automatic mode
+switch-to-manual = Switch to manual
+live = LIVE
+live-tooltip = Read from the camera as it changes
+take-snapshot = Take Snapshot
+current-size = Current size
+snapshot-preview-size = Streaming size
+snapshot-saved = Saved {$filename}
+snapshot-failed = Snapshot failed: {$error}
+start-recording = Record
+stop-recording = Stop Recording
+recording-stats = {$duration} · {$size} · {$frames} frames → {$output}
+recording-failed = Recording failed: {$error}
+show-preview = Show Preview
+hide-preview = Hide Preview
+preview-starting = Starting preview…
+preview-failed = Preview failed: {$error}
+zebra = Zebra
+zebra-level = from {$level}%
+focus-peaking = Focus peaking
+peaking-sensitivity = Sensitivity
+guide-thirds = Thirds
+guide-center = Center
+guide-safe-area = Safe areas
+guide-aspect = Mask
+aspect-none = None
+mirror-preview = Mirror
+virtual-camera = Virtual camera
+no-virtual-outputs = Load the v4l2loopback module to send this camera to other apps
+virtual-camera-failed = Virtual camera stopped: {$error}
+transform-zoom = Zoom
+transform-pan = Pan
+transform-tilt = Tilt
+transform-brightness = Brightness
+transform-contrast = Contrast
+transform-rotation = Rotation
+transform-mirror = Mirror
+reset-transform = Reset
+ptz = Pan, tilt and zoom
+ptz-pan = Pan
+ptz-tilt = Tilt
+ptz-zoom = Zoom
+ptz-home = Center
+preset-name = Preset name
+save-preset = Save Position
+delete-preset = Delete preset
+preset-default-name = Preset {$number}
+calibrate-aim = Calibrate Aim
+calibrating = Calibrating, keep the scene still…
+calibrated = Calibrated: {$fov}° wide at the widest zoom
+calibration-failed = Could not calibrate; try again with more detail in view
+aim-hint = Click the preview to aim, scroll to zoom. Assuming {$fov}° wide.
+focus-assistant = Focus
+find-focus = Find Focus
+stop-focus = Stop
+focus-region = Region
+finding-focus = Sweeping focus… {$percent}%
+focus-found = Sharpest at {$value}
+focus-stopped = Focus sweep stopped
+focus-hint = Sweeps the focus range with autofocus off and keeps the sharpest position
+pick-white = Pick White
+pick-white-hint = Click something white or grey in the preview
+setting-white-balance = Balancing white…
+white-balance-unsupported = This camera has no manual white balance to set
+white-balance-temperature = {$value} K
+white-balance-red-blue = red {$red}, blue {$blue}
+white-balance-set = White balance set: {$value}
+white-balance-closest = Could not get fully neutral; closest is {$value}
+software-auto = Software auto
+auto-exposure = Exposure
+auto-white-balance = White balance
+software-auto-hint = Runs while the app is open, or without it through cosmic-cam --daemon
+auto-target = Brightness
+auto-max-exposure = Max exposure
+auto-max-gain = Max gain
+auto-warmth = Warmth
+auto-speed = Speed
+auto-damping = Damping
+detect-flicker = Detect Flicker
+stop-flicker = Stop
+detecting-flicker = Looking for flicker… {$percent}%
+flicker-found = The lighting flickers at {$frequency} mains
+flicker-none = No flicker found
+flicker-unclear = There is banding, but it doesn't match 50 or 60 Hz mains
+flicker-hint = Tries a few exposures to find the power line frequency
+use-frequency = Use {$frequency}
+freeze-reference = Freeze Reference
+freeze-reference-hint = Keeps this frame and the control values to compare changes against
+clear-reference = Clear Reference
+wipe = Divider
+reference-values = Reference values
+showing-a = A: the device has the reference values
+showing-b = B: the device has the current values
+stream-stats = Stream statistics
+stream-stats-idle = Statistics show while the camera is streaming
+stream-fps = {$fps} fps measured, {$nominal} fps negotiated
+stream-frames = {$frames} frames, {$dropped} dropped by the camera or bus, {$skipped} undecodable, {$late} delayed by the app
+stream-jitter = Frame interval varies by {$jitter} ms; red bars took over one and a half intervals
+scopes = Scopes
+scope-histogram = Histogram
+scope-parade = RGB Parade
+scope-waveform = Waveform
+scope-vectorscope = Vectorscope
+scopes-waiting = Waiting for frames…
+
+## Device info
+device-info = Device Info
+copy-device-info = Copy to clipboard
+device = Device
+info-card = Card
+info-driver = Driver
+info-version = Driver version
+info-bus = Bus
+info-node = Device node
+info-capabilities = Capabilities
+info-usb = USB
+info-usb-id = Vendor:Product ID
+info-manufacturer = Manufacturer
+info-product = Product
+info-serial = Serial number
+info-usb-speed = USB speed
+info-firmware = Firmware (bcdDevice)
+info-formats = Formats and resolutions
+info-controls = Controls per class
+ungrouped-controls = Ungrouped
 
 ## Menu
 view = View
 menu-about = About
 menu-settings = Settings
+menu-next-device = Next camera
+menu-previous-device = Previous camera
+menu-save-profile = Save profile
+menu-switch-profile = Switch profile
+menu-reset-control = Reset control
+menu-reset-group = Reset group
+menu-reset-device = Reset all controls
+menu-toggle-preview = Toggle preview
 close-to-tray = Close to tray
+
+## Settings
+shortcuts = Keyboard shortcuts
+live-controls = Live controls
+poll-interval = Refresh interval
+poll-interval-description = How often values the camera changes on its own are read back
+poll-off = Off
+poll-interval-ms = {$ms} ms
+preset-transition = Preset recall time
+preset-transition-description = How long recalling a preset takes to move the camera there
+preset-jump = Instant
+scope-interval = Scope refresh interval
+scope-interval-description = How often the scopes are redrawn from the camera's frames
+recording = Recording
+recording-folder = Output folder
+recording-folder-description = Recordings are saved here; leave empty for the videos folder
+shortcut-none = Not set
+shortcut-invalid = Not a valid shortcut, e.g. Ctrl+Shift+R
+shortcut-conflict = Already used for {$action}
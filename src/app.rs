@@ -1,26 +1,61 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
 
 use crate::content::{self, Content};
 use crate::device::*;
+use crate::info::{self, DeviceInfo};
+use crate::key_binds;
+use crate::link::{self, LinkStatus, LinkWrite};
+use crate::loopback;
+use crate::record;
+use crate::scopes;
 use crate::settings;
-use crate::storage::{get_save_filename, save_device_state};
+use crate::snapshot;
+use crate::storage::{
+    get_device_id, get_favorites_filename, get_presets_filename, get_profile_filename,
+    get_save_filename, load_config, load_device_state, load_favorites, load_presets, save_config,
+    save_device_state, save_favorites, save_presets,
+};
+use crate::stream;
 use crate::fl;
 use cosmic::app::{context_drawer, message, Task, Core};
-use cosmic::iced::Alignment;
+use cosmic::iced::keyboard::{self, Key, Modifiers};
+use cosmic::iced::{event, Alignment, Event, Subscription};
 use cosmic::widget::{self, icon, menu, nav_bar};
-use cosmic::{cosmic_theme, theme, Application, ApplicationExt, Element};
+use cosmic::{cosmic_theme, iced, theme, Application, ApplicationExt, Element};
+use tokio::sync::watch;
 
 const REPOSITORY: &str = "https://github.com/dylf/miniature-bassoon";
 
 pub struct App {
     core: Core,
     context_page: ContextPage,
-    key_binds: HashMap<menu::KeyBind, MenuAction>,
+    pub(crate) config: settings::Config,
+    pub(crate) key_binds: HashMap<menu::KeyBind, MenuAction>,
+    /// Shortcuts being typed that aren't valid, or clash with another action, yet.
+    pub(crate) shortcut_edits: HashMap<MenuAction, String>,
+    pub(crate) poll_interval_labels: Vec<String>,
+    pub(crate) scope_interval_labels: Vec<String>,
+    pub(crate) preset_transition_labels: Vec<String>,
     nav: nav_bar::Model,
     content: Content,
-    selected_device: Option<VideoDevice>,
+    selected_device: Option<Arc<VideoDevice>>,
+    pub(crate) device_info: Option<DeviceInfo>,
+    /// Paths of the linked cameras; a change on one is applied to all of them.
+    linked: HashSet<String>,
+    /// Per linked camera, the controls that didn't take the value and why.
+    link_deviations: HashMap<String, BTreeMap<String, String>>,
+    /// Linked cameras with a write running.
+    link_in_flight: HashSet<String>,
+    /// Values for each linked camera waiting for its running write, in order.
+    link_pending: HashMap<String, Vec<(String, u32, v4l::control::Value)>>,
+    /// The controls of each linked camera, kept between writes.
+    link_devices: HashMap<String, Arc<VideoDevice>>,
+    /// Where the stream of the selected camera goes besides the preview.
+    stream_sinks: watch::Sender<stream::Sinks>,
 }
 
 #[derive(Debug, Clone)]
@@ -29,6 +64,17 @@ pub enum Message {
     LaunchUrl(String),
     ToggleContextPage(ContextPage),
     Setting(settings::Message),
+    Key(Modifiers, Key, bool),
+    CopyDeviceInfo,
+    DeviceLoaded(Result<Arc<VideoDevice>, String>),
+    DeviceInfoLoaded(Result<DeviceInfo, String>),
+    LinkWritten(String, LinkWrite),
+    ControlsPolled(String, Result<Vec<(u32, i64)>, String>),
+    ControlsReadBack(String, Result<Vec<(u32, i64)>, String>),
+    /// Something loaded for the device at the path, for the device page.
+    DeviceDataLoaded(String, content::Message),
+    NextDevice,
+    PreviousDevice,
 }
 
 pub enum Page {
@@ -39,7 +85,8 @@ pub enum Page {
 pub enum ContextPage {
     #[default]
     About,
-    Settings
+    Settings,
+    DeviceInfo,
 }
 
 impl ContextPage {
@@ -47,14 +94,73 @@ impl ContextPage {
         match self {
             Self::About => fl!("menu-about"),
             Self::Settings => fl!("menu-settings"),
+            Self::DeviceInfo => fl!("device-info"),
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub enum MenuAction {
     About,
     ToggleSettings,
+    NextDevice,
+    PreviousDevice,
+    SaveProfile,
+    SwitchProfile,
+    ResetControl,
+    ResetGroup,
+    ResetDevice,
+    TogglePreview,
+}
+
+impl MenuAction {
+    pub const ALL: &'static [MenuAction] = &[
+        MenuAction::NextDevice,
+        MenuAction::PreviousDevice,
+        MenuAction::SaveProfile,
+        MenuAction::SwitchProfile,
+        MenuAction::ResetControl,
+        MenuAction::ResetGroup,
+        MenuAction::ResetDevice,
+        MenuAction::TogglePreview,
+        MenuAction::ToggleSettings,
+        MenuAction::About,
+    ];
+
+    /// Stable name used to store the action's shortcut.
+    pub fn id(&self) -> &'static str {
+        match self {
+            MenuAction::About => "about",
+            MenuAction::ToggleSettings => "toggle-settings",
+            MenuAction::NextDevice => "next-device",
+            MenuAction::PreviousDevice => "previous-device",
+            MenuAction::SaveProfile => "save-profile",
+            MenuAction::SwitchProfile => "switch-profile",
+            MenuAction::ResetControl => "reset-control",
+            MenuAction::ResetGroup => "reset-group",
+            MenuAction::ResetDevice => "reset-device",
+            MenuAction::TogglePreview => "toggle-preview",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.iter().find(|action| action.id() == id).copied()
+    }
+
+    pub fn title(&self) -> String {
+        match self {
+            MenuAction::About => fl!("menu-about"),
+            MenuAction::ToggleSettings => fl!("menu-settings"),
+            MenuAction::NextDevice => fl!("menu-next-device"),
+            MenuAction::PreviousDevice => fl!("menu-previous-device"),
+            MenuAction::SaveProfile => fl!("menu-save-profile"),
+            MenuAction::SwitchProfile => fl!("menu-switch-profile"),
+            MenuAction::ResetControl => fl!("menu-reset-control"),
+            MenuAction::ResetGroup => fl!("menu-reset-group"),
+            MenuAction::ResetDevice => fl!("menu-reset-device"),
+            MenuAction::TogglePreview => fl!("menu-toggle-preview"),
+        }
+    }
 }
 
 impl menu::action::MenuAction for MenuAction {
@@ -64,6 +170,14 @@ impl menu::action::MenuAction for MenuAction {
         match self {
             MenuAction::About => Message::ToggleContextPage(ContextPage::About),
             MenuAction::ToggleSettings => Message::ToggleContextPage(ContextPage::Settings),
+            MenuAction::NextDevice => Message::NextDevice,
+            MenuAction::PreviousDevice => Message::PreviousDevice,
+            MenuAction::SaveProfile => Message::Content(content::Message::Save),
+            MenuAction::SwitchProfile => Message::Content(content::Message::NextProfile),
+            MenuAction::ResetControl => Message::Content(content::Message::ResetControl),
+            MenuAction::ResetGroup => Message::Content(content::Message::ResetGroup),
+            MenuAction::ResetDevice => Message::Content(content::Message::ResetDevice),
+            MenuAction::TogglePreview => Message::Content(content::Message::TogglePreview),
         }
     }
 }
@@ -102,17 +216,29 @@ impl Application for App {
         });
 
 
+        let config = load_config();
         let mut app = App {
             core,
             context_page: ContextPage::default(),
-            key_binds: HashMap::new(),
+            key_binds: key_binds::key_binds(&config.key_binds),
+            shortcut_edits: HashMap::new(),
+            poll_interval_labels: settings::poll_interval_labels(),
+            scope_interval_labels: settings::scope_interval_labels(),
+            preset_transition_labels: settings::preset_transition_labels(),
+            config,
             nav,
             content: Content::new(),
             selected_device: None,
+            device_info: None,
+            linked: HashSet::new(),
+            link_deviations: HashMap::new(),
+            link_in_flight: HashSet::new(),
+            link_pending: HashMap::new(),
+            link_devices: HashMap::new(),
+            stream_sinks: watch::Sender::new(stream::Sinks::default()),
         };
-        app.set_device_from_nav();
-
-        let command = app.update_titles();
+        app.content.set_preset_transition(Duration::from_millis(app.config.preset_transition));
+        let command = Task::batch([app.update_titles(), app.load_selected_device()]);
 
         (app, command)
     }
@@ -128,6 +254,17 @@ impl Application for App {
             menu::items(
                 &self.key_binds,
                 vec![
+                    menu::Item::Button(fl!("menu-next-device"), None, MenuAction::NextDevice),
+                    menu::Item::Button(fl!("menu-previous-device"), None, MenuAction::PreviousDevice),
+                    menu::Item::Divider,
+                    menu::Item::Button(fl!("menu-save-profile"), None, MenuAction::SaveProfile),
+                    menu::Item::Button(fl!("menu-switch-profile"), None, MenuAction::SwitchProfile),
+                    menu::Item::Divider,
+                    menu::Item::Button(fl!("menu-reset-control"), None, MenuAction::ResetControl),
+                    menu::Item::Button(fl!("menu-reset-group"), None, MenuAction::ResetGroup),
+                    menu::Item::Button(fl!("menu-reset-device"), None, MenuAction::ResetDevice),
+                    menu::Item::Divider,
+                    menu::Item::Button(fl!("menu-toggle-preview"), None, MenuAction::TogglePreview),
                     menu::Item::Button(fl!("menu-settings"), None, MenuAction::ToggleSettings),
                     menu::Item::Divider,
                     menu::Item::Button(fl!("menu-about"), None, MenuAction::About),
@@ -166,36 +303,240 @@ impl Application for App {
                     self.context_page = context_page;
                     self.core.window.show_context = true;
                 }
+                if context_page == ContextPage::DeviceInfo {
+                    return self.load_device_info();
+                }
+            }
+            Message::CopyDeviceInfo => {
+                if let Some(info) = &self.device_info {
+                    return cosmic::iced::clipboard::write(info.to_string());
+                }
+            }
+            Message::DeviceLoaded(Ok(dev)) => {
+                if !self.is_active_path(&dev.path) {
+                    return Task::none();
+                }
+                // Fresher than what linked writes last read, if the camera is linked.
+                if self.linked.contains(&dev.path) {
+                    self.link_devices.insert(dev.path.clone(), dev.clone());
+                }
+                let changed = self.selected_device.as_ref().map(|old| &old.path) != Some(&dev.path);
+                if !changed {
+                    self.content.device_refreshed();
+                    self.selected_device = Some(dev);
+                    return Task::none();
+                }
+                // The camera left behind shouldn't keep the reference values or
+                // the exposure flicker detection was trying.
+                let restore = match self.selected_device.clone() {
+                    Some(old) => match self.content.restore_temporary_values(&old) {
+                        Some(task) => self.content_task(old, task),
+                        None => Task::none(),
+                    },
+                    None => Task::none(),
+                };
+                self.content.select_device(&dev);
+                self.content.set_guides(
+                    self.config.guides.get(&get_device_id(&dev)).copied().unwrap_or_default()
+                );
+                self.content.set_fov(self.config.fov.get(&get_device_id(&dev)).copied());
+                self.content.set_auto(self.config.auto.get(&get_device_id(&dev)).copied().unwrap_or_default());
+                let path = dev.path.clone();
+                // Dropped if another device is selected before they finish.
+                let device_loads = iced::Task::batch([
+                    iced::Task::perform(load_favorites(get_favorites_filename(&dev)), content::Message::FavoritesLoaded),
+                    iced::Task::perform(stream::load_frame_sizes(dev.path.clone()), content::Message::FrameSizes),
+                    iced::Task::perform(load_presets(get_presets_filename(&dev)), content::Message::PresetsLoaded),
+                    iced::Task::perform(
+                        load_device_state(get_save_filename(&dev)),
+                        |res| content::Message::TransformLoaded(res.ok().and_then(|save_data| save_data.transform)),
+                    ),
+                ]).map(move |message| message::app(Message::DeviceDataLoaded(path.clone(), message)));
+                let loads = Task::batch([
+                    restore,
+                    device_loads,
+                    Task::perform(
+                        loopback::load_outputs(),
+                        |outputs| message::app(Message::Content(content::Message::OutputsLoaded(outputs))),
+                    ),
+                ]);
+                self.selected_device = Some(dev);
+                self.update_links();
+                if self.context_page == ContextPage::DeviceInfo {
+                    return Task::batch([loads, self.load_device_info()]);
+                }
+                return loads;
+            }
+            Message::DeviceLoaded(Err(err)) => {
+                println!("Could not load device: {}", err);
+            }
+            Message::DeviceInfoLoaded(Ok(info)) => {
+                if self.is_active_path(&info.path) {
+                    self.device_info = Some(info);
+                }
+            }
+            Message::LinkWritten(path, written) => {
+                self.link_in_flight.remove(&path);
+                match written.device {
+                    Some(device) if self.linked.contains(&path) => {
+                        self.link_devices.insert(path.clone(), device);
+                    }
+                    _ => {
+                        self.link_devices.remove(&path);
+                    }
+                }
+                let deviations = self.link_deviations.entry(path.clone()).or_default();
+                for (name, result) in written.results {
+                    match result.deviation() {
+                        Some(deviation) => {
+                            deviations.insert(name, deviation);
+                        }
+                        None => {
+                            deviations.remove(&name);
+                        }
+                    }
+                }
+                self.update_links();
+                if let Some(values) = self.link_pending.remove(&path) {
+                    return self.write_linked(path, values);
+                }
+            }
+            Message::ControlsPolled(path, result) => {
+                if self.selected_device.as_ref().is_some_and(|dev| dev.path == path) {
+                    return self.update(Message::Content(content::Message::Polled(result)));
+                }
+            }
+            Message::ControlsReadBack(path, result) => {
+                if self.selected_device.as_ref().is_some_and(|dev| dev.path == path) {
+                    return self.update(Message::Content(content::Message::ReadBack(result)));
+                }
+            }
+            Message::DeviceDataLoaded(path, message) => {
+                if self.is_active_path(&path) {
+                    return self.update(Message::Content(message));
+                }
+            }
+            Message::DeviceInfoLoaded(Err(err)) => {
+                println!("Could not read device info: {}", err);
+                self.device_info = None;
             }
             Message::Content(message) => {
-                let dev = &self.selected_device;
+                let dev = self.selected_device.clone();
                 if let Some(dev) = dev {
-                    let content_command = self.content.update(dev, message);
-                    self.set_device_from_nav();
-                    let dev = self.selected_device.as_ref().unwrap();
-                    if let Some(content::Task::Save) = content_command {
-                        let save_data = get_device_save_data(dev);
-                        if let Ok(save_data) = save_data {
-                            let filename = get_save_filename(dev);
-                            return Task::perform
-                                (save_device_state(filename, save_data),
-                                    |_| message::none() );
-                        } else {
-                            return Task::none();
-                        }
-                    };
+                    if let Some(task) = self.content.update(&dev, message) {
+                        return self.content_task(dev, task);
+                    }
                 }
             }
             Message::Setting(message) => {
                 let setting_command = self.update_settings(message);
+                self.content.set_preset_transition(Duration::from_millis(self.config.preset_transition));
                 if let Some(settings::Task::Save) = setting_command {
+                    return Task::perform(save_config(self.config.clone()), |_| message::none());
+                }
+            }
+            Message::Key(modifiers, key, captured) => {
+                // The step keys go to the focused integer entry, and nowhere else.
+                let nudge = match key {
+                    _ if !modifiers.is_empty() || !self.content.has_focused_entry() => None,
+                    Key::Named(keyboard::key::Named::ArrowUp) => Some(content::Nudge::Up),
+                    Key::Named(keyboard::key::Named::ArrowDown) => Some(content::Nudge::Down),
+                    Key::Named(keyboard::key::Named::PageUp) => Some(content::Nudge::PageUp),
+                    Key::Named(keyboard::key::Named::PageDown) => Some(content::Nudge::PageDown),
+                    _ => None,
+                };
+                if let Some(nudge) = nudge {
+                    return self.update(Message::Content(content::Message::NudgeFocused(nudge)));
+                }
+                if captured {
                     return Task::none();
                 }
+                let action = self.key_binds.iter()
+                    .find(|(key_bind, _)| key_bind.matches(modifiers, &key))
+                    .map(|(_, action)| *action);
+                if let Some(action) = action {
+                    return self.update(menu::action::MenuAction::message(&action));
+                }
+                // Number keys recall the PTZ presets in order.
+                let preset = match &key {
+                    Key::Character(c) if modifiers.is_empty() => c.as_str().parse::<usize>().ok(),
+                    _ => None,
+                };
+                if let Some(preset @ 1..=9) = preset {
+                    return self.update(Message::Content(content::Message::RecallPreset(preset - 1)));
+                }
             }
+            Message::NextDevice => return self.select_relative_device(1),
+            Message::PreviousDevice => return self.select_relative_device(-1),
         }
         Task::none()
     }
 
+    fn subscription(&self) -> Subscription<Self::Message> {
+        let mut subscriptions = vec![
+            // Focused text fields capture key presses, but an integer entry
+            // still takes the step keys.
+            event::listen_with(|event, status, _| match event {
+                Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) => {
+                    Some(Message::Key(modifiers, key, status == event::Status::Captured))
+                }
+                _ => None,
+            }),
+        ];
+
+        // Poll volatile and auto-driven controls while a device page is shown.
+        let poll = self.selected_device.as_ref()
+            .is_some_and(|dev| !get_live_controls(&dev.controls).is_empty())
+            || self.content.is_ptz_moving();
+        if poll && self.config.poll_interval > 0 {
+            subscriptions.push(
+                cosmic::iced::time::every(Duration::from_millis(self.config.poll_interval))
+                    .map(|_| Message::Content(content::Message::Poll)),
+            );
+        }
+
+        // Steps a smooth move to a PTZ preset.
+        if self.content.is_transitioning() {
+            subscriptions.push(
+                cosmic::iced::time::every(content::TRANSITION_TICK)
+                    .map(|_| Message::Content(content::Message::TransitionTick)),
+            );
+        }
+
+        if self.selected_device.is_some() && self.content.show_scopes() {
+            subscriptions.push(
+                cosmic::iced::time::every(Duration::from_millis(self.config.scope_interval))
+                    .map(|_| Message::Content(content::Message::UpdateScopes)),
+            );
+        }
+
+        if let Some(dev) = &self.selected_device {
+            let recording = self.content.recording().map(|format| record::Recording {
+                format,
+                folder: settings::get_recording_folder(&self.config),
+                name: dev.name.clone(),
+            });
+            let sinks = stream::Sinks { recording, output: self.content.virtual_output() };
+            // A running stream picks the change up from here rather than restarting.
+            self.stream_sinks.send_if_modified(|current| {
+                let changed = *current != sinks;
+                *current = sinks;
+                changed
+            });
+            if self.content.is_streaming() {
+                subscriptions.push(
+                    stream::frames(
+                        dev.path.clone(),
+                        self.stream_sinks.subscribe(),
+                        self.content.transform_receiver(),
+                    ).map(|event| Message::Content(content::Message::Stream(event))),
+                );
+            }
+        }
+
+        Subscription::batch(subscriptions)
+    }
+
     fn context_drawer(&self) -> Option<context_drawer::ContextDrawer<Self::Message>> {
         if !self.core.window.show_context {
             return None;
@@ -210,29 +551,283 @@ impl Application for App {
                 self.settings(),
                 Message::ToggleContextPage(ContextPage::Settings),
             ).title(fl!("menu-settings")),
+            ContextPage::DeviceInfo => context_drawer::context_drawer(
+                self.device_info(),
+                Message::ToggleContextPage(ContextPage::DeviceInfo),
+            ).title(fl!("device-info")),
         })
     }
 
     fn on_nav_select(&mut self, id: nav_bar::Id) -> Task<Self::Message> {
         self.nav.activate(id);
-        self.set_device_from_nav();
-        self.update_titles()
+        Task::batch([self.update_titles(), self.load_selected_device()])
     }
 }
 
 impl App {
 
-    fn set_device_from_nav(&mut self) {
+    fn select_relative_device(&mut self, offset: isize) -> Task<Message> {
+        let ids: Vec<nav_bar::Id> = self.nav.iter().collect();
+        let Some(position) = ids.iter().position(|id| *id == self.nav.active()) else {
+            return Task::none();
+        };
+        let next = (position as isize + offset).rem_euclid(ids.len() as isize);
+        self.on_nav_select(ids[next as usize])
+    }
+
+    /// Sends changes made on the selected camera to every other linked camera.
+    fn write_to_linked<'a>(
+        &mut self,
+        dev: &VideoDevice,
+        values: impl IntoIterator<Item = (u32, &'a v4l::control::Value)>,
+    ) -> Task<Message> {
+        if !self.linked.contains(&dev.path) {
+            return Task::none();
+        }
+        let named: Vec<(String, u32, &v4l::control::Value)> = values.into_iter()
+            .filter_map(|(id, value)| Some((find_control(&dev.controls, id)?.name().to_string(), id, value)))
+            .collect();
+        let paths: Vec<String> = self.linked.iter()
+            .filter(|path| **path != dev.path)
+            .cloned()
+            .collect();
+        Task::batch(paths.into_iter().map(|path| {
+            let values = named.iter()
+                .filter_map(|(name, id, value)| Some((name.clone(), *id, link::copy_value(value)?)))
+                .collect();
+            self.write_linked(path, values)
+        }))
+    }
+
+    /// Like content writes, a linked write waits for the previous one on the
+    /// same camera, and only the latest value of each control is written next.
+    fn write_linked(&mut self, path: String, values: Vec<(String, u32, v4l::control::Value)>) -> Task<Message> {
+        if values.is_empty() {
+            return Task::none();
+        }
+        if self.link_in_flight.contains(&path) {
+            let pending = self.link_pending.entry(path).or_default();
+            for (name, id, value) in values {
+                pending.retain(|(queued, _, _)| *queued != name);
+                pending.push((name, id, value));
+            }
+            return Task::none();
+        }
+        self.link_in_flight.insert(path.clone());
+        let device = self.link_devices.get(&path).cloned();
+        Task::perform(
+            link::write_linked(path.clone(), device, values),
+            move |written| message::app(Message::LinkWritten(path.clone(), written)),
+        )
+    }
+
+    /// Refreshes the link panel of the device page and flags deviating cameras in the nav bar.
+    fn update_links(&mut self) {
+        let selected = self.selected_device.as_ref().map(|dev| dev.path.clone());
+        let ids: Vec<nav_bar::Id> = self.nav.iter().collect();
+        let mut links = Vec::new();
+        for id in ids {
+            let Some(Page::VideoDeviceForm(path)) = self.nav.data::<Page>(id) else {
+                continue;
+            };
+            let path = path.clone();
+            let deviations: Vec<(String, String)> = match self.linked.contains(&path) {
+                true => self.link_deviations.get(&path)
+                    .map(|deviations| deviations.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+                    .unwrap_or_default(),
+                false => Vec::new(),
+            };
+            let icon = if deviations.is_empty() { "camera-web-symbolic" } else { "dialog-warning-symbolic" };
+            self.nav.icon_set(id, icon::from_name(icon).into());
+            if self.linked.contains(&path) && Some(&path) != selected.as_ref() {
+                links.push(LinkStatus {
+                    name: self.nav.text(id).unwrap_or_default().to_string(),
+                    deviations,
+                });
+            }
+        }
+        let linked = selected.is_some_and(|path| self.linked.contains(&path));
+        self.content.set_links(linked, links);
+    }
+
+    fn active_path(&self) -> Option<&String> {
         match self.nav.data(self.nav.active()) {
-            Some(Page::VideoDeviceForm(dev_path)) => {
-                let dev = get_device_by_path(dev_path).unwrap();
-                self.selected_device = Some(dev);
+            Some(Page::VideoDeviceForm(dev_path)) => Some(dev_path),
+            _ => None,
+        }
+    }
+
+    /// Loaded results may arrive after the user has moved to another device.
+    fn is_active_path(&self, path: &str) -> bool {
+        self.active_path().is_some_and(|active| active == path)
+    }
+
+    /// Carries out what the device page asked for.
+    fn content_task(&mut self, dev: Arc<VideoDevice>, task: content::Task) -> Task<Message> {
+        match task {
+            content::Task::ShowInfo => self.update(Message::ToggleContextPage(ContextPage::DeviceInfo)),
+            content::Task::Save(profile) => {
+                let filename = get_profile_filename(&dev, &profile);
+                let transform = self.content.transform();
+                Task::perform(
+                    async move {
+                        let mut save_data = read_save_data(dev).await?;
+                        save_data.transform = Some(transform);
+                        save_device_state(filename, save_data).await.map_err(|e| format!("{}", e))
+                    },
+                    |res| match res {
+                        Ok(()) => message::app(Message::Content(content::Message::Saved)),
+                        Err(err) => {
+                            println!("Could not save profile: {}", err);
+                            message::none()
+                        }
+                    },
+                )
             }
-            _ => {
+            content::Task::LoadProfile(profile) => {
+                let filename = get_profile_filename(&dev, &profile);
+                Task::perform(
+                    load_device_state(filename),
+                    |res| message::app(Message::Content(content::Message::ProfileLoaded(res))),
+                )
+            }
+            content::Task::Write(id, value) => {
+                let linked = self.write_to_linked(&dev, [(id, &value)]);
+                Task::batch([
+                    Task::perform(
+                        write_control(dev, id, value),
+                        move |res| message::app(Message::Content(content::Message::Written(id, res))),
+                    ),
+                    linked,
+                ])
+            }
+            content::Task::Writes(values) => {
+                let linked = self.write_to_linked(&dev, values.iter().map(|(id, value)| (*id, value)));
+                let writes: Vec<Task<Message>> = values.into_iter().map(|(id, value)| {
+                    Task::perform(
+                        write_control(dev.clone(), id, value),
+                        move |res| message::app(Message::Content(content::Message::Written(id, res))),
+                    )
+                }).chain([linked]).collect();
+                Task::batch(writes)
+            }
+            content::Task::UnlinkedWrites(values) => {
+                let writes: Vec<Task<Message>> = values.into_iter().map(|(id, value)| {
+                    Task::perform(
+                        write_control(dev.clone(), id, value),
+                        move |res| message::app(Message::Content(content::Message::Written(id, res))),
+                    )
+                }).collect();
+                Task::batch(writes)
+            }
+            content::Task::SavePresets(presets) => {
+                Task::perform(
+                    save_presets(get_presets_filename(&dev), presets),
+                    |_| message::none(),
+                )
+            }
+            content::Task::ToggleLink => {
+                if self.linked.remove(&dev.path) {
+                    self.link_deviations.remove(&dev.path);
+                    self.link_devices.remove(&dev.path);
+                    self.link_pending.remove(&dev.path);
+                } else {
+                    self.linked.insert(dev.path.clone());
+                }
+                self.update_links();
+                Task::none()
+            }
+            content::Task::WriteMany(values) => {
+                let ids: Vec<u32> = values.iter().map(|(id, _)| *id).collect();
+                let linked = self.write_to_linked(&dev, values.iter().map(|(id, value)| (*id, value)));
+                Task::batch([
+                    Task::perform(
+                        write_controls(dev, values),
+                        move |res| message::app(Message::Content(content::Message::WrittenMany(ids.clone(), res))),
+                    ),
+                    linked,
+                ])
+            }
+            content::Task::ScrollTo(id, offset) => cosmic::iced::widget::scrollable::scroll_to(id, offset),
+            content::Task::SaveFavorites(favorites) => {
+                Task::perform(
+                    save_favorites(get_favorites_filename(&dev), favorites),
+                    |_| message::none(),
+                )
+            }
+            content::Task::Poll(ids) => {
+                let path = dev.path.clone();
+                Task::perform(
+                    read_controls(path.clone(), ids),
+                    move |res| message::app(Message::ControlsPolled(path.clone(), res)),
+                )
+            }
+            content::Task::Snapshot(request) => {
+                Task::perform(
+                    snapshot::take_snapshot(dev.path.clone(), request),
+                    |res| {
+                        let res = res.map(|filename| filename.display().to_string());
+                        message::app(Message::Content(content::Message::SnapshotSaved(res)))
+                    },
+                )
+            }
+            content::Task::Scopes(frame, kinds) => {
+                Task::perform(
+                    scopes::compute_scopes(frame, kinds),
+                    |res| message::app(Message::Content(content::Message::ScopesComputed(res))),
+                )
+            }
+            content::Task::SaveGuides(guides) => {
+                self.config.guides.insert(get_device_id(&dev), guides);
+                Task::perform(save_config(self.config.clone()), |_| message::none())
+            }
+            content::Task::SaveFov(fov) => {
+                self.config.fov.insert(get_device_id(&dev), fov);
+                Task::perform(save_config(self.config.clone()), |_| message::none())
+            }
+            content::Task::SaveAuto(auto) => {
+                self.config.auto.insert(get_device_id(&dev), auto);
+                Task::perform(save_config(self.config.clone()), |_| message::none())
+            }
+            content::Task::Batch(tasks) => {
+                let tasks: Vec<Task<Message>> = tasks.into_iter()
+                    .map(|task| self.content_task(dev.clone(), task))
+                    .collect();
+                Task::batch(tasks)
+            }
+            content::Task::ReadBack(ids) => {
+                let path = dev.path.clone();
+                Task::perform(
+                    read_controls(path.clone(), ids),
+                    move |res| message::app(Message::ControlsReadBack(path.clone(), res)),
+                )
+            }
+            content::Task::Refresh => self.load_selected_device(),
+        }
+    }
+
+    fn load_selected_device(&self) -> Task<Message> {
+        match self.active_path() {
+            Some(dev_path) => Task::perform(
+                load_device(dev_path.clone()),
+                |res| message::app(Message::DeviceLoaded(res)),
+            ),
+            None => {
                 println!("Something terrible has occured!");
+                Task::none()
             }
         }
     }
+
+    fn load_device_info(&self) -> Task<Message> {
+        match &self.selected_device {
+            Some(dev) => Task::perform(
+                info::load_device_info(dev.clone()),
+                |res| message::app(Message::DeviceInfoLoaded(res)),
+            ),
+            None => Task::none(),
+        }
+    }
     pub fn about(&self) -> Element<Message> {
         let cosmic_theme::Spacing { space_xxs, .. } = theme::active().cosmic().spacing;
 
@@ -0,0 +1,296 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::time::{Duration, Instant};
+
+use v4l::control::Value as ControlValue;
+
+use crate::cid;
+use crate::device::{find_control, ControlData, DeviceControls, IntegerControl};
+use crate::scopes::luma;
+use crate::stream::Frame;
+
+/// How often the loop corrects; faster than the camera settles only makes it hunt.
+const UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+/// Pixels sampled along each axis at most.
+const MAX_SAMPLES: u32 = 160;
+/// Brightness errors smaller than this, in log ratio, are left alone.
+const EXPOSURE_DEADBAND: f64 = 0.05;
+const WHITE_DEADBAND: f64 = 0.02;
+/// Share of the gain range moved per unit of log brightness error.
+const GAIN_RATE: f64 = 0.5;
+/// Share of a white balance range moved per unit of log color error.
+const WHITE_RATE: f64 = 0.5;
+
+/// Targets and tuning for the software auto exposure and white balance, saved per device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoSettings {
+    pub exposure: bool,
+    pub white_balance: bool,
+    /// Mean brightness to aim for, in percent.
+    pub target: u32,
+    /// Warmer (positive) or cooler (negative) than neutral, from -50 to 50.
+    pub warmth: i32,
+    /// Share of the remaining error corrected at each update, in percent.
+    pub speed: u32,
+    /// How much each measurement is smoothed with the ones before, in percent.
+    pub damping: u32,
+    /// Highest exposure and gain the loop may use, in percent of their ranges.
+    pub max_exposure: u32,
+    pub max_gain: u32,
+}
+
+impl Default for AutoSettings {
+    fn default() -> Self {
+        Self {
+            exposure: false,
+            white_balance: false,
+            target: 45,
+            warmth: 0,
+            speed: 30,
+            damping: 50,
+            max_exposure: 100,
+            max_gain: 50,
+        }
+    }
+}
+
+impl AutoSettings {
+    /// Parses the form written by `to_config`, e.g. `exposure=1,target=45,speed=30`.
+    pub fn from_config(value: &str) -> Self {
+        let mut settings = AutoSettings::default();
+        for (key, value) in value.split(',').filter_map(|item| item.trim().split_once('=')) {
+            match key {
+                "exposure" => settings.exposure = value == "1",
+                "white_balance" => settings.white_balance = value == "1",
+                "target" => settings.target = value.parse().unwrap_or(settings.target).clamp(5, 95),
+                "warmth" => settings.warmth = value.parse().unwrap_or(0).clamp(-50, 50),
+                "speed" => settings.speed = value.parse().unwrap_or(settings.speed).clamp(1, 100),
+                "damping" => settings.damping = value.parse().unwrap_or(settings.damping).min(95),
+                "max_exposure" => settings.max_exposure = value.parse().unwrap_or(100).clamp(1, 100),
+                "max_gain" => settings.max_gain = value.parse().unwrap_or(settings.max_gain).min(100),
+                _ => (),
+            }
+        }
+        settings
+    }
+
+    pub fn to_config(&self) -> String {
+        format!(
+            "exposure={},white_balance={},target={},warmth={},speed={},damping={},max_exposure={},max_gain={}",
+            self.exposure as u8,
+            self.white_balance as u8,
+            self.target,
+            self.warmth,
+            self.speed,
+            self.damping,
+            self.max_exposure,
+            self.max_gain,
+        )
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.exposure || self.white_balance
+    }
+
+    /// Writes that switch off the camera's own automation for what the loop drives.
+    fn manual_modes(&self, controls: &[DeviceControls]) -> Vec<(u32, ControlValue)> {
+        let mut writes = Vec::new();
+        let mut manual = |id, value| {
+            if find_control(controls, id).is_some_and(|control| !control.flags().contains(v4l::control::Flags::READ_ONLY)) {
+                writes.push((id, value));
+            }
+        };
+        if self.exposure {
+            manual(cid::EXPOSURE_AUTO, ControlValue::Integer(cid::EXPOSURE_MANUAL));
+            manual(cid::AUTOGAIN, ControlValue::Boolean(false));
+        }
+        if self.white_balance {
+            manual(cid::AUTO_WHITE_BALANCE, ControlValue::Boolean(false));
+        }
+        writes
+    }
+}
+
+/// Mean brightness, weighting the middle of the frame double, and the mean
+/// color of the pixels that aren't clipped.
+fn measure(frame: &Frame) -> (f64, [f64; 3]) {
+    let step_x = (frame.width / MAX_SAMPLES).max(1);
+    let step_y = (frame.height / MAX_SAMPLES).max(1);
+    let (mut brightness, mut weights) = (0.0, 0.0);
+    let (mut color, mut count) = ([0.0; 3], 0.0);
+    for y in (0..frame.height).step_by(step_y as usize) {
+        for x in (0..frame.width).step_by(step_x as usize) {
+            let i = ((y * frame.width + x) * 4) as usize;
+            let Some(px) = frame.pixels.get(i..i + 4) else {
+                continue;
+            };
+            let level = luma(px);
+            let middle = (frame.width / 3..frame.width * 2 / 3).contains(&x)
+                && (frame.height / 3..frame.height * 2 / 3).contains(&y);
+            let weight = if middle { 2.0 } else { 1.0 };
+            brightness += level as f64 * weight;
+            weights += weight;
+            if (16..240).contains(&level) {
+                color.iter_mut().zip(px).for_each(|(sum, c)| *sum += *c as f64);
+                count += 1.0;
+            }
+        }
+    }
+    let brightness = if weights > 0.0 { brightness / weights } else { 0.0 };
+    let color = if count > 0.0 { color.map(|sum| sum / count) } else { [128.0; 3] };
+    (brightness, color)
+}
+
+/// Log ratio of two channel levels, safe for black.
+fn log_ratio(a: f64, b: f64) -> f64 {
+    (a.max(1.0) / b.max(1.0)).ln()
+}
+
+/// The share of a control's range its upper limit allows, as a value.
+fn limit(control: &IntegerControl, percent: u32) -> i64 {
+    control.min + ((control.max - control.min) as f64 * percent as f64 / 100.0) as i64
+}
+
+/// Drives exposure, gain and white balance from frame statistics, toward the targets in `AutoSettings`.
+#[derive(Debug, Clone, Default)]
+pub struct AutoLoop {
+    brightness: Option<f64>,
+    color: Option<[f64; 3]>,
+    last_update: Option<Instant>,
+    /// Whether the camera's own automation has been switched off.
+    manual: bool,
+}
+
+impl AutoLoop {
+    /// Starts over, switching the camera's automation off again on the next update.
+    pub fn reset(&mut self) {
+        *self = AutoLoop::default();
+    }
+
+    /// Measures the frame and returns the writes that move toward the targets,
+    /// at most once per update interval. `current` gives the value in effect.
+    pub fn update(
+        &mut self,
+        settings: &AutoSettings,
+        frame: &Frame,
+        controls: &[DeviceControls],
+        current: impl Fn(&IntegerControl) -> i64,
+    ) -> Vec<(u32, ControlValue)> {
+        if !settings.is_active() || self.last_update.is_some_and(|last| last.elapsed() < UPDATE_INTERVAL) {
+            return Vec::new();
+        }
+        self.last_update = Some(Instant::now());
+
+        let (brightness, color) = measure(frame);
+        let keep = settings.damping as f64 / 100.0;
+        let brightness = self.brightness.map_or(brightness, |smoothed| smoothed * keep + brightness * (1.0 - keep));
+        let color = match self.color {
+            Some(smoothed) => [0, 1, 2].map(|c| smoothed[c] * keep + color[c] * (1.0 - keep)),
+            None => color,
+        };
+        self.brightness = Some(brightness);
+        self.color = Some(color);
+
+        let mut writes = Vec::new();
+        if !self.manual {
+            self.manual = true;
+            writes.extend(settings.manual_modes(controls));
+        }
+        let integer = |id| match find_control(controls, id) {
+            Some(DeviceControls::Integer(control)) if !control.flags().contains(v4l::control::Flags::READ_ONLY) => Some(control),
+            _ => None,
+        };
+        let speed = settings.speed as f64 / 100.0;
+
+        if settings.exposure {
+            let error = log_ratio(settings.target as f64 * 255.0 / 100.0, brightness);
+            if error.abs() > EXPOSURE_DEADBAND {
+                let exposure = integer(cid::EXPOSURE_ABSOLUTE).or_else(|| integer(cid::EXPOSURE));
+                let values = Self::expose(error * speed, settings, exposure, integer(cid::GAIN), &current);
+                writes.extend(values.into_iter().map(|(id, value)| (id, ControlValue::Integer(value))));
+            }
+        }
+
+        if settings.white_balance {
+            // Warmth shifts the target between red and blue, split evenly around green.
+            let warmth = settings.warmth as f64 / 100.0;
+            let [r, g, b] = color;
+            let mut correct = |control: Option<&IntegerControl>, error: f64| {
+                let Some(control) = control else {
+                    return;
+                };
+                if error.abs() <= WHITE_DEADBAND {
+                    return;
+                }
+                let value = current(control);
+                let range = (control.max - control.min) as f64;
+                let next = control.snap(value - (range * error * speed * WHITE_RATE).round() as i64);
+                if next != value {
+                    writes.push((control.id, ControlValue::Integer(next)));
+                }
+            };
+            match (integer(cid::RED_BALANCE), integer(cid::BLUE_BALANCE)) {
+                (Some(red), Some(blue)) => {
+                    correct(Some(red), log_ratio(r, g) - warmth / 2.0);
+                    correct(Some(blue), log_ratio(b, g) + warmth / 2.0);
+                }
+                // Too blue means the camera assumes warmer light than there is,
+                // so the error is negative and the temperature goes up.
+                _ => correct(integer(cid::WHITE_BALANCE_TEMPERATURE), log_ratio(r, b) - warmth),
+            }
+        }
+        writes
+    }
+
+    /// Spreads a brightness change, in log ratio, over exposure and gain:
+    /// exposure first when brightening, since gain adds noise, and gain
+    /// first when darkening.
+    fn expose(
+        change: f64,
+        settings: &AutoSettings,
+        exposure: Option<&IntegerControl>,
+        gain: Option<&IntegerControl>,
+        current: &impl Fn(&IntegerControl) -> i64,
+    ) -> Vec<(u32, i64)> {
+        let mut writes = Vec::new();
+        let mut remaining = change;
+        let mut apply_exposure = |remaining: &mut f64| {
+            let Some(control) = exposure else {
+                return;
+            };
+            let value = current(control);
+            let upper = limit(control, settings.max_exposure).max(control.min);
+            let next = control.snap(((value.max(1) as f64) * remaining.exp()).round() as i64).clamp(control.min, upper);
+            if next != value {
+                *remaining -= log_ratio(next as f64, value as f64);
+                writes.push((control.id, next));
+            }
+        };
+        let mut gain_writes = Vec::new();
+        let mut apply_gain = |remaining: &mut f64| {
+            let Some(control) = gain else {
+                return;
+            };
+            let value = current(control);
+            let range = (control.max - control.min) as f64;
+            if range <= 0.0 {
+                return;
+            }
+            let upper = limit(control, settings.max_gain).max(control.min);
+            let next = control.snap(value + (range * *remaining * GAIN_RATE).round() as i64).clamp(control.min, upper);
+            if next != value {
+                *remaining -= (next - value) as f64 / (range * GAIN_RATE);
+                gain_writes.push((control.id, next));
+            }
+        };
+        if change > 0.0 {
+            apply_exposure(&mut remaining);
+            apply_gain(&mut remaining);
+        } else {
+            apply_gain(&mut remaining);
+            apply_exposure(&mut remaining);
+        }
+        writes.append(&mut gain_writes);
+        writes
+    }
+}
@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! V4L2 control ids from `linux/v4l2-controls.h`, for the controls the app
+//! treats specially.
+
+pub const HUE: u32 = 0x00980903;
+pub const AUTO_WHITE_BALANCE: u32 = 0x0098090c;
+pub const RED_BALANCE: u32 = 0x0098090e;
+pub const BLUE_BALANCE: u32 = 0x0098090f;
+pub const EXPOSURE: u32 = 0x00980911;
+pub const AUTOGAIN: u32 = 0x00980912;
+pub const GAIN: u32 = 0x00980913;
+pub const POWER_LINE_FREQUENCY: u32 = 0x00980918;
+pub const HUE_AUTO: u32 = 0x00980919;
+pub const WHITE_BALANCE_TEMPERATURE: u32 = 0x0098091a;
+
+pub const EXPOSURE_AUTO: u32 = 0x009a0901;
+pub const EXPOSURE_ABSOLUTE: u32 = 0x009a0902;
+pub const PAN_RELATIVE: u32 = 0x009a0904;
+pub const TILT_RELATIVE: u32 = 0x009a0905;
+pub const PAN_RESET: u32 = 0x009a0906;
+pub const TILT_RESET: u32 = 0x009a0907;
+pub const PAN_ABSOLUTE: u32 = 0x009a0908;
+pub const TILT_ABSOLUTE: u32 = 0x009a0909;
+pub const FOCUS_ABSOLUTE: u32 = 0x009a090a;
+pub const FOCUS_RELATIVE: u32 = 0x009a090b;
+pub const FOCUS_AUTO: u32 = 0x009a090c;
+pub const ZOOM_ABSOLUTE: u32 = 0x009a090d;
+pub const ZOOM_RELATIVE: u32 = 0x009a090e;
+pub const ZOOM_CONTINUOUS: u32 = 0x009a090f;
+pub const PAN_SPEED: u32 = 0x009a0920;
+pub const TILT_SPEED: u32 = 0x009a0921;
+
+/// `V4L2_EXPOSURE_MANUAL`, the manual entry of the `EXPOSURE_AUTO` menu.
+pub const EXPOSURE_MANUAL: i64 = 1;
+
+/// Entries of the `POWER_LINE_FREQUENCY` menu.
+pub const POWER_LINE_DISABLED: i64 = 0;
+pub const POWER_LINE_50HZ: i64 = 1;
+pub const POWER_LINE_60HZ: i64 = 2;
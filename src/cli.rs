@@ -0,0 +1,135 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use v4l::control::Value as ControlValue;
+
+use crate::auto::AutoLoop;
+use crate::device::{get_device_by_path, get_devices, set_control_val};
+use crate::snapshot::{self, SnapshotFormat, SnapshotRequest};
+use crate::storage::{get_device_id, load_config};
+use crate::stream;
+
+const USAGE: &str = "\
+Usage: cosmic-cam --snapshot [options]
+       cosmic-cam --daemon [--device <path>]
+
+Saves a still from a camera, with its control values as metadata.
+
+Options:
+  --device <path>     Camera to use, e.g. /dev/video0 (default: the first one)
+  --format <format>   png or jpeg (default: png)
+  --size <WxH>        Frame size to capture at (default: the current one)
+  --output <folder>   Where to save (default: the pictures folder)
+
+With --daemon, runs the software auto exposure and white balance set up in
+the app for the camera until stopped, without opening a window.";
+
+/// Runs a command line action instead of the app, returning its exit code.
+/// Returns `None` when there is nothing to do on the command line.
+pub fn run(args: &[String]) -> Option<i32> {
+    match args.first().map(String::as_str) {
+        Some("--snapshot") => Some(report(snapshot(&args[1..]))),
+        Some("--daemon") => Some(report(daemon(&args[1..]))),
+        Some("--help" | "-h") => {
+            println!("{}", USAGE);
+            Some(0)
+        }
+        _ => None,
+    }
+}
+
+fn report(result: Result<String, String>) -> i32 {
+    match result {
+        Ok(message) => {
+            println!("{}", message);
+            0
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    }
+}
+
+fn snapshot(args: &[String]) -> Result<String, String> {
+    let mut device = None;
+    let mut request = SnapshotRequest::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {}\n\n{}", arg, USAGE));
+        match arg.as_str() {
+            "--device" => device = Some(value()?.clone()),
+            "--format" => {
+                let format = value()?;
+                request.format = SnapshotFormat::from_name(format)
+                    .ok_or(format!("Unknown format: {}", format))?;
+            }
+            "--size" => {
+                let size = value()?;
+                request.size = Some(parse_size(size).ok_or(format!("Not a size: {}", size))?);
+            }
+            "--output" => request.folder = Some(PathBuf::from(value()?)),
+            _ => return Err(format!("Unknown option: {}\n\n{}", arg, USAGE)),
+        }
+    }
+
+    let device = device_path(device)?;
+    let filename = snapshot::save_snapshot(&device, request)?;
+    Ok(format!("Saved {}", filename.display()))
+}
+
+/// The given device, or the first camera if none was given.
+fn device_path(device: Option<String>) -> Result<String, String> {
+    match device {
+        Some(device) => Ok(device),
+        None => get_devices()
+            .first()
+            .map(|device| device.path.clone())
+            .ok_or(String::from("No camera found")),
+    }
+}
+
+fn daemon(args: &[String]) -> Result<String, String> {
+    let mut device = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--device" => device = Some(args.next().ok_or(format!("Missing value for {}\n\n{}", arg, USAGE))?.clone()),
+            _ => return Err(format!("Unknown option: {}\n\n{}", arg, USAGE)),
+        }
+    }
+
+    let device = get_device_by_path(&device_path(device)?)?;
+    let settings = load_config().auto.get(&get_device_id(&device)).copied().unwrap_or_default();
+    if !settings.is_active() {
+        return Err(format!("Software auto exposure and white balance are off for {}; turn them on in the app first", device.name));
+    }
+    println!("Adjusting {} until stopped", device.name);
+
+    let mut auto = AutoLoop::default();
+    // What we last wrote, since the device isn't read back while streaming.
+    let mut written: HashMap<u32, i64> = HashMap::new();
+    stream::watch_frames(&device.path, |frame| {
+        let writes = auto.update(&settings, &frame, &device.controls, |control| {
+            written.get(&control.id).copied().unwrap_or(control.value)
+        });
+        for (id, value) in writes {
+            if let ControlValue::Integer(value) = value {
+                written.insert(id, value);
+            }
+            if let Err(err) = set_control_val(&device, id, value) {
+                eprintln!("Could not set control {}: {}", id, err);
+            }
+        }
+        true
+    })?;
+    Ok(String::new())
+}
+
+/// Parses sizes like `1280x720`.
+fn parse_size(size: &str) -> Option<(u32, u32)> {
+    let (width, height) = size.split_once(['x', 'X', '×'])?;
+    Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+}
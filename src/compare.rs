@@ -0,0 +1,35 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::stream::Frame;
+
+const DIVIDER: [u8; 4] = [255, 255, 255, 255];
+
+/// A frozen frame and the control values it was taken with, to compare changes against.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub frame: Frame,
+    /// As from `get_current_values`.
+    pub values: Vec<(u32, i64)>,
+}
+
+/// The reference left of the divider and the live frame right of it, the
+/// divider sitting at `wipe` percent of the width. `flipped` puts the
+/// reference on the right instead, for a preview that gets mirrored after.
+/// `None` when the frames differ in size.
+pub fn split(reference: &Frame, live: &Frame, wipe: u32, flipped: bool) -> Option<Frame> {
+    if live.width == 0 || (reference.width, reference.height) != (live.width, live.height) {
+        return None;
+    }
+    let width = live.width as usize;
+    let divider = width * wipe.min(100) as usize / 100;
+    let divider = if flipped { width - divider } else { divider };
+    let mut pixels = live.pixels.clone();
+    for (row, reference_row) in pixels.chunks_exact_mut(width * 4).zip(reference.pixels.chunks_exact(width * 4)) {
+        let (from, to) = if flipped { (divider, width) } else { (0, divider) };
+        row[from * 4..to * 4].copy_from_slice(&reference_row[from * 4..to * 4]);
+        if let Some(px) = row.get_mut(divider.min(width - 1) * 4..divider.min(width - 1) * 4 + 4) {
+            px.copy_from_slice(&DIVIDER);
+        }
+    }
+    Some(Frame { width: live.width, height: live.height, pixels })
+}
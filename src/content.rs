@@ -1,68 +1,572 @@
+use crate::auto::{AutoLoop, AutoSettings};
+use crate::cid;
+use crate::compare::{self, Reference};
 use crate::device;
 use crate::device::*;
 use crate::fl;
+use crate::flicker::{DetectStep, Detection, Flicker, Mains};
+use crate::focus::{self, FocusRegion, Sweep, SweepStep};
+use crate::link::LinkStatus;
+use crate::loopback::{Rotation, Transform};
+use crate::overlays::{AspectRatio, Guides, Overlays, PeakingColor};
+use crate::ptz::{self, Axis, Calibration, Preset, Ptz, Transition};
+use crate::record::{RecordingFormat, RecordingStats};
+use crate::scopes::{ScopeImage, ScopeKind};
+use crate::snapshot::{SnapshotFormat, SnapshotRequest};
+use crate::stats::StreamStats;
+use crate::storage::{self, SaveData};
+use crate::stream;
+use crate::white_balance::{Balance, BalanceStep, Neutralize};
 use crate::widgets;
-use cosmic::iced::Alignment;
+use cosmic::iced::mouse::ScrollDelta;
+use cosmic::iced::widget::mouse_area;
+use cosmic::iced::widget::scrollable::{AbsoluteOffset, Viewport};
+use cosmic::iced::{Alignment, Length, Point};
 use cosmic::widget;
 use cosmic::{theme, Element};
+use std::collections::{HashMap, HashSet};
 use std::f32;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use v4l::control::Value as ControlValue;
+
+const PREVIEW_WIDTH: f32 = 480.0;
+const SCOPE_WIDTH: f32 = 320.0;
+/// How often a smooth move to a preset writes the next position.
+pub const TRANSITION_TICK: Duration = Duration::from_millis(50);
 
 pub struct Content {
-    open_dialog: OpenDialog,
+    focused_control: Option<u32>,
+    /// The integer entry with keyboard focus, which the arrow and page keys step.
+    focused_entry: Option<u32>,
+    drafts: HashMap<u32, String>,
+    /// Controls with a write running in the background.
+    in_flight: HashSet<u32>,
+    /// The latest value asked for while a write was running, and whether it goes
+    /// to linked cameras too; only this one gets written next.
+    pending: HashMap<u32, (ControlValue, bool)>,
+    /// Values shown until the device has been read back after writing.
+    requested: HashMap<u32, i64>,
+    /// Values of volatile and auto-driven controls read by polling.
+    live_values: HashMap<u32, i64>,
+    /// Values read back after writing, newer than those of the loaded device.
+    read_back: HashMap<u32, i64>,
+    polling: bool,
+    /// Axes the PTZ pad is moving at speed; their positions are polled meanwhile.
+    ptz_moving: HashSet<Axis>,
+    presets: Vec<Preset>,
+    preset_name: String,
+    /// How long recalling a preset takes; zero jumps straight there.
+    preset_transition: Duration,
+    transition: Option<Transition>,
+    /// Horizontal field of view at the widest zoom, for aiming by clicking the preview.
+    fov: f32,
+    calibration: Option<Calibration>,
+    calibration_status: Option<String>,
+    /// Where the pointer is over the preview.
+    preview_cursor: Option<Point>,
+    focus_region: FocusRegion,
+    focus_sweep: Option<Sweep>,
+    /// Sharpness against focus position from the last sweep.
+    focus_curve: Option<widget::image::Handle>,
+    focus_status: Option<String>,
+    /// Whether the next click on the preview picks a neutral point instead of aiming.
+    picking_white: bool,
+    white_balance: Option<Neutralize>,
+    white_balance_status: Option<String>,
+    flicker_detection: Option<Detection>,
+    flicker: Option<Flicker>,
+    filter: String,
+    /// Pinned control ids, in the order they were pinned.
+    favorites: Vec<u32>,
+    show_favorites_popover: bool,
+    linked: bool,
+    /// The other linked cameras and how far they've drifted from this one.
+    links: Vec<LinkStatus>,
+    scroll_id: widget::Id,
+    /// Where the unfiltered form was scrolled to, restored when the filter is cleared.
+    scroll_offset: AbsoluteOffset,
+    profiles: Vec<String>,
+    profile: String,
+    show_preview: bool,
+    preview: Option<widget::image::Handle>,
+    preview_error: Option<String>,
+    overlays: Overlays,
+    guides: Guides,
+    /// "None" followed by `AspectRatio::ALL`.
+    aspect_labels: Vec<String>,
+    /// Shown left of the live picture, for before and after comparisons.
+    reference: Option<Reference>,
+    /// Where the divider between reference and live sits, in percent of the width.
+    wipe: u32,
+    /// The values to go back to while the reference values are applied.
+    live_values_before_reference: Option<Vec<(u32, i64)>>,
+    /// The latest streamed frame without overlays, saved as is when taking a snapshot while streaming.
+    last_frame: Option<stream::Frame>,
+    snapshot_format: SnapshotFormat,
+    /// Frame sizes offered for snapshots; the first entry keeps the current size.
+    snapshot_sizes: Vec<Option<(u32, u32)>>,
+    snapshot_size_labels: Vec<String>,
+    snapshot_size: usize,
+    taking_snapshot: bool,
+    snapshot_status: Option<String>,
+    recording: bool,
+    recording_format: RecordingFormat,
+    recording_stats: Option<RecordingStats>,
+    recording_error: Option<String>,
+    virtual_camera: bool,
+    /// Paths of the v4l2loopback nodes, and their names for the dropdown.
+    outputs: Vec<String>,
+    output_labels: Vec<String>,
+    output: usize,
+    output_error: Option<String>,
+    transform: Transform,
+    /// Hands transform changes to a running stream without restarting it.
+    transform_sender: watch::Sender<Transform>,
+    /// Scopes shown beside the controls, in `ScopeKind::ALL` order.
+    scopes: Vec<ScopeKind>,
+    scope_images: Vec<(ScopeKind, widget::image::Handle)>,
+    computing_scopes: bool,
+    show_stats: bool,
+    stream_stats: Option<StreamStats>,
+    /// Frame intervals of the latest stats.
+    stats_graph: Option<widget::image::Handle>,
+    /// Software auto exposure and white balance, run on the streamed frames.
+    auto: AutoSettings,
+    auto_loop: AutoLoop,
 }
 
-#[derive(Debug, Clone)]
-pub enum OpenDialog {
-    None,
-    Info,
+/// Keyboard or button steps for integer controls.
+#[derive(Debug, Clone, Copy)]
+pub enum Nudge {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
-    Slider(u32, f32),
+    Integer(u32, i64),
+    IntegerInput(u32, String),
+    IntegerSubmit(u32),
+    Nudge(u32, Nudge),
+    NudgeFocused(Nudge),
+    EntryFocus(u32, bool),
     Boolean(u32, bool),
     Menu(u32, u32),
     ButtonPress(u32),
-    OpenDialog(OpenDialog),
+    ShowInfo,
     Save,
+    Saved,
+    ProfileName(String),
+    SelectProfile(usize),
+    NextProfile,
+    ProfileLoaded(Result<SaveData, String>),
+    Written(u32, Result<(), String>),
+    WrittenMany(Vec<u32>, Result<(), String>),
+    ReadBack(Result<Vec<(u32, i64)>, String>),
+    ResetControl,
+    ResetGroup,
+    ResetDevice,
+    TogglePreview,
+    Filter(String),
+    ToggleFavorite(u32),
+    ToggleFavoritesPopover,
+    FavoritesLoaded(Vec<u32>),
+    ToggleLink,
+    SwitchToManual(u32),
+    TakeSnapshot,
+    ToggleRecording,
+    RecordingFormat(usize),
+    SnapshotFormat(usize),
+    SnapshotSize(usize),
+    FrameSizes(Result<Vec<(u32, u32)>, String>),
+    SnapshotSaved(Result<String, String>),
+    ToggleScope(ScopeKind),
+    ToggleStats(bool),
+    FreezeReference,
+    ClearReference,
+    Wipe(u32),
+    ApplyReference(bool),
+    PtzStart(Axis, i64),
+    PtzStop(Axis),
+    PtzHome,
+    PresetsLoaded(Vec<Preset>),
+    PresetName(String),
+    SavePreset,
+    RecallPreset(usize),
+    DeletePreset(usize),
+    TransitionTick,
+    PreviewCursor(Option<Point>),
+    AimAtCursor,
+    PreviewScroll(ScrollDelta),
+    Calibrate,
+    FindFocus,
+    StopFocus,
+    FocusRegion(usize),
+    ToggleAutoExposure(bool),
+    ToggleAutoWhiteBalance(bool),
+    AutoTarget(u32),
+    AutoWarmth(i32),
+    AutoSpeed(u32),
+    AutoDamping(u32),
+    AutoMaxExposure(u32),
+    AutoMaxGain(u32),
+    PickWhite,
+    SetWhite,
+    DetectFlicker,
+    StopFlicker,
+    UseMains(Mains),
+    ToggleVirtualCamera(bool),
+    VirtualOutput(usize),
+    OutputsLoaded(Result<Vec<(String, String)>, String>),
+    TransformLoaded(Option<Transform>),
+    Zoom(u32),
+    PanX(i32),
+    PanY(i32),
+    Rotation(usize),
+    TransformMirror(bool),
+    TransformBrightness(i32),
+    TransformContrast(i32),
+    ResetTransform,
+    ToggleZebra(bool),
+    ZebraLevel(u8),
+    TogglePeaking(bool),
+    PeakingLevel(u8),
+    PeakingColor(usize),
+    ToggleThirds(bool),
+    ToggleCenter(bool),
+    ToggleSafeArea(bool),
+    AspectMask(usize),
+    ToggleMirror(bool),
+    UpdateScopes,
+    ScopesComputed(Result<Vec<ScopeImage>, String>),
+    Poll,
+    Polled(Result<Vec<(u32, i64)>, String>),
+    Scrolled(Viewport),
+    Stream(stream::Event),
     None,
 }
 
 pub enum Task {
-    Save,
+    ShowInfo,
+    Save(String),
+    LoadProfile(String),
+    Write(u32, ControlValue),
+    /// Independent writes, each answered with its own `Written`.
+    Writes(Vec<(u32, ControlValue)>),
+    /// Like `Writes`, but kept off linked cameras: values an assistant or
+    /// automatic mode worked out for this camera only.
+    UnlinkedWrites(Vec<(u32, ControlValue)>),
+    WriteMany(Vec<(u32, ControlValue)>),
+    ScrollTo(widget::Id, AbsoluteOffset),
+    SaveFavorites(Vec<u32>),
+    ToggleLink,
+    Poll(Vec<u32>),
+    Snapshot(SnapshotRequest),
+    Scopes(stream::Frame, Vec<ScopeKind>),
+    SaveGuides(Guides),
+    SavePresets(Vec<Preset>),
+    SaveFov(f32),
+    SaveAuto(AutoSettings),
+    Batch(Vec<Task>),
+    /// Reads the given controls again after writing them.
+    ReadBack(Vec<u32>),
+    /// Reloads the whole device.
+    Refresh,
 }
 
 impl Content {
     pub fn new() -> Self {
         Self {
-            open_dialog: OpenDialog::None,
+            focused_control: None,
+            focused_entry: None,
+            drafts: HashMap::new(),
+            in_flight: HashSet::new(),
+            pending: HashMap::new(),
+            requested: HashMap::new(),
+            live_values: HashMap::new(),
+            read_back: HashMap::new(),
+            polling: false,
+            ptz_moving: HashSet::new(),
+            presets: Vec::new(),
+            preset_name: String::new(),
+            preset_transition: Duration::ZERO,
+            transition: None,
+            fov: ptz::DEFAULT_FOV,
+            calibration: None,
+            calibration_status: None,
+            preview_cursor: None,
+            focus_region: FocusRegion::default(),
+            focus_sweep: None,
+            focus_curve: None,
+            focus_status: None,
+            picking_white: false,
+            white_balance: None,
+            white_balance_status: None,
+            flicker_detection: None,
+            flicker: None,
+            filter: String::new(),
+            favorites: Vec::new(),
+            show_favorites_popover: false,
+            linked: false,
+            links: Vec::new(),
+            scroll_id: widget::Id::unique(),
+            scroll_offset: AbsoluteOffset::default(),
+            profiles: vec![storage::DEFAULT_PROFILE.to_string()],
+            profile: storage::DEFAULT_PROFILE.to_string(),
+            show_preview: false,
+            preview: None,
+            preview_error: None,
+            overlays: Overlays::default(),
+            guides: Guides::default(),
+            aspect_labels: std::iter::once(fl!("aspect-none"))
+                .chain(AspectRatio::ALL.iter().map(|aspect| aspect.as_ref().to_string()))
+                .collect(),
+            reference: None,
+            wipe: 50,
+            live_values_before_reference: None,
+            last_frame: None,
+            snapshot_format: SnapshotFormat::default(),
+            snapshot_sizes: vec![None],
+            snapshot_size_labels: vec![fl!("current-size")],
+            snapshot_size: 0,
+            taking_snapshot: false,
+            snapshot_status: None,
+            recording: false,
+            recording_format: RecordingFormat::default(),
+            recording_stats: None,
+            recording_error: None,
+            virtual_camera: false,
+            outputs: Vec::new(),
+            output_labels: Vec::new(),
+            output: 0,
+            output_error: None,
+            transform: Transform::default(),
+            transform_sender: watch::Sender::new(Transform::default()),
+            scopes: Vec::new(),
+            scope_images: Vec::new(),
+            computing_scopes: false,
+            show_stats: false,
+            stream_stats: None,
+            stats_graph: None,
+            auto: AutoSettings::default(),
+            auto_loop: AutoLoop::default(),
         }
     }
 
-    fn title(&self) -> Element<Message> {
-        widget::text::title1(fl!("welcome")).into()
+    /// Resets the per-device state when another device is selected.
+    pub fn select_device(&mut self, dev: &VideoDevice) {
+        self.focused_control = None;
+        self.focused_entry = None;
+        self.drafts.clear();
+        self.in_flight.clear();
+        self.pending.clear();
+        self.requested.clear();
+        self.live_values.clear();
+        self.read_back.clear();
+        self.polling = false;
+        self.ptz_moving.clear();
+        self.presets.clear();
+        self.preset_name.clear();
+        self.transition = None;
+        self.calibration = None;
+        self.calibration_status = None;
+        self.preview_cursor = None;
+        self.focus_sweep = None;
+        self.focus_curve = None;
+        self.focus_status = None;
+        self.picking_white = false;
+        self.white_balance = None;
+        self.white_balance_status = None;
+        self.flicker_detection = None;
+        self.flicker = None;
+        self.stream_stats = None;
+        self.stats_graph = None;
+        self.filter.clear();
+        self.favorites.clear();
+        self.show_favorites_popover = false;
+        self.scroll_offset = AbsoluteOffset::default();
+        self.profiles = storage::get_profiles(dev);
+        self.profile = storage::DEFAULT_PROFILE.to_string();
+        self.preview = None;
+        self.preview_error = None;
+        self.last_frame = None;
+        self.reference = None;
+        self.live_values_before_reference = None;
+        self.snapshot_sizes = vec![None];
+        self.snapshot_size_labels = vec![fl!("current-size")];
+        self.snapshot_size = 0;
+        self.snapshot_status = None;
+        self.recording = false;
+        self.recording_stats = None;
+        self.recording_error = None;
+        self.scope_images.clear();
+        self.virtual_camera = false;
+        self.output_error = None;
+        self.set_transform(Transform::default());
     }
 
-    fn dialog_button<'a>(
-        &self,
-        button_label: impl Into<std::borrow::Cow<'a, str>>,
-        dialog_content: impl Into<std::borrow::Cow<'a, str>>,
-    ) -> Element<'a, Message> {
-        let mut popover = widget::popover(
-            widget::button::standard(
-                button_label
-            ).on_press(Message::OpenDialog(OpenDialog::Info))
-        );
-        if let OpenDialog::Info = self.open_dialog {
-            popover = popover.popup(
-                widget::dialog().body(dialog_content)
-            )
-            .on_close(Message::OpenDialog(OpenDialog::None));
+    /// Called when the device has been read back after a change.
+    pub fn device_refreshed(&mut self) {
+        let (in_flight, pending) = (&self.in_flight, &self.pending);
+        self.requested.retain(|id, _| in_flight.contains(id) || pending.contains_key(id));
+        self.live_values.clear();
+        self.read_back.clear();
+    }
+
+    pub fn set_links(&mut self, linked: bool, links: Vec<LinkStatus>) {
+        self.linked = linked;
+        self.links = links;
+    }
+
+    /// Sets the framing guides saved for the device.
+    pub fn set_guides(&mut self, guides: Guides) {
+        self.guides = guides;
+    }
+
+    pub fn set_auto(&mut self, auto: AutoSettings) {
+        self.auto = auto;
+        self.auto_loop.reset();
+    }
+
+    /// The loopback node to feed while the virtual camera is on.
+    pub fn virtual_output(&self) -> Option<String> {
+        if !self.virtual_camera {
+            return None;
+        }
+        self.outputs.get(self.output).cloned()
+    }
+
+    /// Follows the transform for the virtual camera, which can change while streaming.
+    pub fn transform_receiver(&self) -> watch::Receiver<Transform> {
+        self.transform_sender.subscribe()
+    }
+
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+        self.transform_sender.send_replace(transform);
+    }
+
+    /// Whether an integer entry has keyboard focus, so the step keys go to it.
+    pub fn has_focused_entry(&self) -> bool {
+        self.focused_entry.is_some()
+    }
+
+    /// Whether the PTZ pad is moving the camera and its position should be polled.
+    pub fn is_ptz_moving(&self) -> bool {
+        !self.ptz_moving.is_empty()
+    }
+
+    pub fn set_preset_transition(&mut self, duration: Duration) {
+        self.preset_transition = duration;
+    }
+
+    /// Sets the calibrated field of view of the device, if it has one.
+    pub fn set_fov(&mut self, fov: Option<f32>) {
+        self.fov = fov.unwrap_or(ptz::DEFAULT_FOV);
+    }
+
+    pub fn is_transitioning(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    pub fn show_scopes(&self) -> bool {
+        !self.scopes.is_empty()
+    }
+
+    /// Whether frames are needed, for the preview, a recording or the scopes.
+    pub fn is_streaming(&self) -> bool {
+        self.show_preview || self.recording || self.virtual_camera || self.show_scopes() || self.auto.is_active()
+    }
+
+    /// The format to record in while a recording is running.
+    pub fn recording(&self) -> Option<RecordingFormat> {
+        self.recording.then_some(self.recording_format)
+    }
+
+    /// The value to show for a control: the last one asked for if it hasn't
+    /// been read back yet, otherwise the latest polled or loaded value.
+    fn shown_value(&self, id: u32, device_value: i64) -> i64 {
+        self.requested.get(&id)
+            .or_else(|| self.live_values.get(&id))
+            .or_else(|| self.read_back.get(&id))
+            .copied()
+            .unwrap_or(device_value)
+    }
+
+    /// Starts writing a control, or queues the value if a write for it is
+    /// already running. Only the latest queued value is written.
+    fn request_write(&mut self, id: u32, value: ControlValue) -> Option<Task> {
+        let value = self.start_write(id, value, true)?;
+        Some(Task::Write(id, value))
+    }
+
+    /// Returns the value to write now, or `None` when it was queued.
+    fn start_write(&mut self, id: u32, value: ControlValue, linked: bool) -> Option<ControlValue> {
+        match value {
+            ControlValue::Integer(val) => {
+                self.requested.insert(id, val);
+            }
+            ControlValue::Boolean(val) => {
+                self.requested.insert(id, val as i64);
+            }
+            _ => (),
+        }
+        if self.in_flight.contains(&id) {
+            self.pending.insert(id, (value, linked));
+            return None;
+        }
+        self.in_flight.insert(id);
+        Some(value)
+    }
+
+    /// Reads back just the written controls, or the whole device when an auto
+    /// mode was among them, since that changes which controls are active.
+    fn read_back_task(ids: Vec<u32>) -> Task {
+        match ids.iter().any(|id| is_gate_master(*id)) {
+            true => Task::Refresh,
+            false => Task::ReadBack(ids),
+        }
+    }
+
+    /// Like `request_write` for the assistants and automatic modes, whose
+    /// values are worked out for this camera and don't go to linked ones.
+    fn request_unlinked_write(&mut self, id: u32, value: ControlValue) -> Option<Task> {
+        self.write_values(vec![(id, value)], false)
+    }
+
+    /// Like `request_unlinked_write` for several integer controls at once.
+    fn request_unlinked_writes(&mut self, values: Vec<(u32, i64)>) -> Option<Task> {
+        self.request_unlinked_values(values.into_iter().map(|(id, value)| (id, ControlValue::Integer(value))).collect())
+    }
+
+    /// Like `request_unlinked_write` for several controls of any kind at once.
+    fn request_unlinked_values(&mut self, values: Vec<(u32, ControlValue)>) -> Option<Task> {
+        self.write_values(values, false)
+    }
+
+    fn write_values(&mut self, values: Vec<(u32, ControlValue)>, linked: bool) -> Option<Task> {
+        let writes: Vec<(u32, ControlValue)> = values.into_iter()
+            .filter_map(|(id, value)| Some((id, self.start_write(id, value, linked)?)))
+            .collect();
+        match (writes.is_empty(), linked) {
+            (true, _) => None,
+            (false, true) => Some(Task::Writes(writes)),
+            (false, false) => Some(Task::UnlinkedWrites(writes)),
         }
-        popover.into()
     }
 
-    fn device_controls<'a>(&self, dev: &'a VideoDevice) -> Element<'a, Message> {
+    fn title(&self) -> Element<Message> {
+        widget::text::title1(fl!("welcome")).into()
+    }
+
+    fn device_controls<'a>(&'a self, dev: &'a VideoDevice) -> Element<'a, Message> {
         let spacing = theme::active().cosmic().spacing;
         let form = widget::column()
             .padding([0, spacing.space_s, 0, 0])
@@ -70,186 +574,1858 @@ impl Content {
             .push(widget::text::title2(dev.name.clone()))
             .push(
                 widget::row()
+                    .align_y(Alignment::Center)
+                    .spacing(spacing.space_s)
                     .push(
-                        self.dialog_button(
-                            fl!("show-device-info"),
-                            dev.capabilities.to_string()
-                        )
-                        // widget::popover(
-                        //     widget::button::standard(
-                        //         fl!("show-device-info")
-                        //     )
-                        // ).popup(
-                        //     widget::dialog(dev.capabilities.to_string())
-                        //     // widget::dialog(fl!("show-device-info"))
-                        // )
+                        widget::button::standard(fl!("show-device-info"))
+                            .on_press(Message::ShowInfo)
                     )
-            );
-        let mut groups = 0;
+                    .push(
+                        widget::button::standard(
+                            if self.show_preview { fl!("hide-preview") } else { fl!("show-preview") }
+                        ).on_press(Message::TogglePreview)
+                    )
+                    .push(self.favorites_popover(dev))
+                    .push(
+                        widget::toggler(self.linked)
+                            .label(fl!("link-camera"))
+                            .on_toggle(|_| Message::ToggleLink)
+                    )
+                    .push(widget::text::text(fl!("profile")))
+                    .push(widget::dropdown(
+                        &self.profiles,
+                        self.profiles.iter().position(|p| *p == self.profile),
+                        Message::SelectProfile,
+                    ))
+                    .push(
+                        widget::search_input(fl!("search-controls"), &self.filter)
+                            .on_input(Message::Filter)
+                            .on_clear(Message::Filter(String::new()))
+                            .width(Length::Fixed(240.0))
+                    )
+            )
+            .push(self.snapshot_row())
+            .push(self.recording_row())
+            .push(self.virtual_camera_controls())
+            .push(self.auto_controls())
+            .push(self.scope_toggles())
+            .push(self.stream_stats());
+        let form = if self.show_preview {
+            let form = form.push(self.overlay_controls()).push(self.preview(dev)).push(self.compare_row());
+            [self.focus_assistant(dev), self.white_balance_row(dev), self.flicker_row(dev)]
+                .into_iter()
+                .flatten()
+                .fold(form, |form, row| form.push(row))
+        } else {
+            form
+        };
+        let form = if self.linked {
+            form.push(self.link_status())
+        } else {
+            form
+        };
+        let query = self.filter.trim().to_lowercase();
+        let favorites = self.favorite_controls(dev);
+        let form = if query.is_empty() && !favorites.is_empty() {
+            favorites.into_iter().fold(
+                form.push(widget::text::title3(fl!("favorites"))),
+                |form, control| form.push(self.control_widget(dev, control)),
+            )
+        } else {
+            form
+        };
+        // The pad takes over the PTZ controls, which only show up again when searched for.
+        let pad = Ptz::find(&dev.controls);
+        let form = match pad {
+            Some(pad) if query.is_empty() => form.push(self.ptz_pad(pad)),
+            _ => form,
+        };
+        let hidden: &[u32] = if pad.is_some() && query.is_empty() { &ptz::PTZ_CONTROLS } else { &[] };
         let form = form.push(widget::text::title3(String::from("Controls")));
+        let controls = self.render_controls(dev, &dev.controls, &query, query.is_empty(), hidden);
+        let form = match (controls.is_empty(), query.is_empty()) {
+            (true, true) => form.push(widget::text::text(fl!("no-controls"))),
+            (true, false) => form.push(widget::text::text(fl!("no-matching-controls"))),
+            _ => form,
+        };
+
+        controls.into_iter().fold(form, |form, control| form.push(control))
+            .push(
+                widget::row()
+                    .align_y(Alignment::Center)
+                    .spacing(spacing.space_s)
+                    .push(
+                        widget::text_input(fl!("profile"), &self.profile)
+                            .on_input(Message::ProfileName)
+                            .width(Length::Fixed(200.0))
+                    )
+                    .push(widget::button::standard(fl!("save"))
+                        .on_press(Message::Save)
+                        .padding([spacing.space_xxs, spacing.space_s])
+                    )
+            )
+            .into()
+    }
 
-        dev.controls.iter().fold(form, |form, control| {
+    /// Renders a level of the control tree. Controls can sit inside a class
+    /// group or at the top level, before any class or on drivers that report
+    /// none, and are drawn the same either way. `show_all` is set when the
+    /// search is empty or matched an enclosing group. `hidden` controls are
+    /// drawn elsewhere.
+    fn render_controls<'a>(
+        &'a self,
+        dev: &'a VideoDevice,
+        controls: &'a [DeviceControls],
+        query: &str,
+        show_all: bool,
+        hidden: &[u32],
+    ) -> Vec<Element<'a, Message>> {
+        let mut elements = Vec::new();
+        for control in controls {
             match control {
                 DeviceControls::ControlGroup(group) => {
-                    let form = if groups > 0 {
-                        form.push(widget::divider::horizontal::default())
-                    } else {
-                        form
-                    };
-                    groups += 1;
-
-                    let form = form.push(widget::text::title4(group.name.clone()));
-                    let form = group.controls.iter().fold(form, |form, control| {
-                        match control {
-                            device::DeviceControls::Boolean(control) => {
-                                let val = control.value;
-                                let id = control.id;
-                                let default = control.default;
-                                let disabled = control.is_disabled();
-                                form.push(
-                                    widget::row()
-                                        .align_y(Alignment::Center)
-                                        .spacing(spacing.space_s)
-                                        .push(
-                                            widget::toggler(val)
-                                                .label(control.name.clone())
-                                                .on_toggle(
-                                                    move |x| {
-                                                        Message::Boolean(id, x)
-                                                    }
-                                                )
-                                        ).push(
-                                            widgets::reset_button(Message::Boolean(id, default), fl!("reset-control"), disabled || default == val)
-                                        )
+                    let show_all = show_all || group.name.to_lowercase().contains(query);
+                    let children = self.render_controls(dev, &group.controls, query, show_all, hidden);
+                    if children.is_empty() {
+                        continue;
+                    }
+                    if !elements.is_empty() {
+                        elements.push(widget::divider::horizontal::default().into());
+                    }
+                    elements.push(widget::text::title4(group.name.clone()).into());
+                    elements.extend(children);
+                }
+                control if hidden.contains(&control.id()) => (),
+                control if show_all || control.matches(query) => {
+                    elements.push(self.control_widget(dev, control));
+                }
+                _ => (),
+            }
+        }
+        elements
+    }
+
+    /// The widgets for a single control: its label, value editor, pin and reset buttons.
+    fn control_widget<'a>(&'a self, dev: &'a VideoDevice, control: &'a DeviceControls) -> Element<'a, Message> {
+        let spacing = theme::active().cosmic().spacing;
+        let column = widget::column().spacing(spacing.space_xxs);
+        let pin = widgets::pin_button(control.id(), self.favorites.contains(&control.id()));
+        let live = widgets::live_marker(control.is_live());
+        let column = match control {
+            device::DeviceControls::Boolean(control) => {
+                let id = control.id;
+                let val = self.shown_value(id, control.value as i64) != 0;
+                let default = control.default;
+                let disabled = control.is_disabled();
+                column.push(
+                    widget::row()
+                        .align_y(Alignment::Center)
+                        .spacing(spacing.space_s)
+                        .push(
+                            widget::toggler(val)
+                                .label(control.name.clone())
+                                .on_toggle(
+                                    move |x| {
+                                        Message::Boolean(id, x)
+                                    }
                                 )
+                        ).push(
+                            widgets::busy_indicator(self.in_flight.contains(&id))
+                        ).push(
+                            live
+                        ).push(
+                            pin
+                        ).push(
+                            widgets::reset_button(Message::Boolean(id, default), fl!("reset-control"), disabled || default == val)
+                        )
+                )
+            }
+            device::DeviceControls::Integer(control) => {
+                let min = control.min as f64;
+                let max = control.max as f64;
+                let id = control.id;
+                let value = self.shown_value(id, control.value);
+                let val = value as f64;
+                let default = control.default;
+                let disabled = control.is_disabled();
+                let text = self.drafts.get(&id)
+                    .cloned()
+                    .unwrap_or_else(|| value.to_string());
+                column.push(
+                    widget::row()
+                        .align_y(Alignment::Center)
+                        .spacing(spacing.space_s)
+                        .push(
+                            widget::text::text(control.name.clone())
+                        ).push(
+                            widgets::integer_entry(id, text, disabled)
+                        ).push(
+                            widgets::busy_indicator(self.in_flight.contains(&id))
+                        ).push(
+                            live
+                        ).push(
+                            pin
+                        ).push(
+                            widgets::reset_button(Message::Integer(id, default), fl!("reset-control"), disabled || default == value)
+                        )
+                    ).push(crate::widgets::custom_slider(
+                        min,
+                        max,
+                        val,
+                        control.step.max(1) as f64,
+                        disabled,
+                        move |x| {
+                            if disabled {
+                                return Message::None;
                             }
-                            device::DeviceControls::Integer(control) => {
-                                let min = control.min as f32;
-                                let max = control.max as f32;
-                                let val = control.value as f32;
-                                let default = control.default as f32;
-                                let id = control.id;
-                                let disabled = control.is_disabled();
-                                form.push(
-                                    widget::row()
-                                        .align_y(Alignment::Center)
-                                        .spacing(spacing.space_s)
-                                        .push(
-                                            widget::text::text(format!("{}: {}", control.name, control.value))
-                                        ).push(
-                                            widgets::reset_button(Message::Slider(id, default), fl!("reset-control"), disabled || default == val)
-                                        )
-                                    ).push(crate::widgets::custom_slider(
-                                        min,
-                                        max,
-                                        val,
-                                        control.step as f32,
-                                        disabled,
-                                        move |x| {
-                                            if disabled {
-                                                return Message::None;
-                                            }
-                                            Message::Slider(id, x)
-                                        })
-                                    )
-                            },
-                            device::DeviceControls::Menu(control) => {
-                                let val = control.menu_items.iter().position(|x| x.id == (control.value.unwrap_or(0) as u32));
-                                let default = control.default as u32;
-                                let ctrl_val = control.value.unwrap_or(0) as u32;
-                                let disabled = control.is_disabled();
-                                let id = control.id;
-                                form.push(
-                                    widget::row()
-                                        .align_y(Alignment::Center)
-                                        .spacing(spacing.space_s)
-                                        .push(
-                                            widget::text::text(control.name.clone())
-                                        ).push(
-                                            widgets::reset_button(
-                                                Message::Menu(id, default),
-                                                fl!("reset-control"),
-                                                disabled || default == ctrl_val
-                                            )
-                                        )
-                                    )
-                                    .push(widget::dropdown(&control.menu_items, val, move |x| {
-                                        let item = control.menu_items[x].id;
-                                        Message::Menu(id, item)
-                                    }))
-                            },
-                            device::DeviceControls::Button(control) => {
-                                let id = control.id;
-                                form.push(
-                                    widget::button::standard(control.name.clone())
-                                        .on_press(Message::ButtonPress(id))
-                                        .padding([spacing.space_xxs, spacing.space_s])
-                                )
-                            },
-                            device::DeviceControls::Control(control) => {
-                                form.push(
-                                    widget::text::text(
-                                        format!(
-                                            "No Widget {}: {:?} - {:?}",
-                                            control.name,
-                                            control.control_type,
-                                            control
-                                        )
-                                    )
-                                )
-                            } 
-                            _ => form
-                        }
-                    });
-                    form
-                }
-                DeviceControls::Control(control) => {
-                    form.push(widget::text::text(control.name.clone()))
+                            Message::Integer(id, x.round() as i64)
+                        })
+                    )
+            },
+            device::DeviceControls::Menu(control) => {
+                let id = control.id;
+                let ctrl_val = self.shown_value(id, control.value.unwrap_or(0) as i64) as u32;
+                let val = control.menu_items.iter().position(|x| x.id == ctrl_val);
+                let default = control.default as u32;
+                let disabled = control.is_disabled();
+                column.push(
+                    widget::row()
+                        .align_y(Alignment::Center)
+                        .spacing(spacing.space_s)
+                        .push(
+                            widget::text::text(control.name.clone())
+                        ).push(
+                            widgets::busy_indicator(self.in_flight.contains(&id))
+                        ).push(
+                            live
+                        ).push(
+                            pin
+                        ).push(
+                            widgets::reset_button(
+                                Message::Menu(id, default),
+                                fl!("reset-control"),
+                                disabled || default == ctrl_val
+                            )
+                        )
+                    )
+                    .push(widget::dropdown(&control.menu_items, val, move |x| {
+                        let item = control.menu_items[x].id;
+                        Message::Menu(id, item)
+                    }))
+            },
+            device::DeviceControls::Button(control) => {
+                let id = control.id;
+                column.push(
+                    widget::row()
+                        .align_y(Alignment::Center)
+                        .spacing(spacing.space_s)
+                        .push(
+                            widget::button::standard(control.name.clone())
+                                .on_press(Message::ButtonPress(id))
+                                .padding([spacing.space_xxs, spacing.space_s])
+                        ).push(
+                            pin
+                        )
+                )
+            },
+            device::DeviceControls::Control(control) => {
+                column.push(
+                    widget::row()
+                        .align_y(Alignment::Center)
+                        .spacing(spacing.space_s)
+                        .push(
+                            widget::text::text(control.name.clone())
+                        ).push(
+                            widget::text::caption(
+                                fl!("unsupported-control", kind = format!("{:?}", control.control_type))
+                            )
+                        ).push(
+                            pin
+                        )
+                )
+            }
+            device::DeviceControls::ControlGroup(_) => column,
+        };
+        match self.disabled_note(dev, control) {
+            Some(note) => column.push(note).into(),
+            None => column.into(),
+        }
+    }
+
+    /// Says why a control can't be changed and, when an automatic mode is the
+    /// cause, offers to switch it to manual.
+    fn disabled_note<'a>(&'a self, dev: &'a VideoDevice, control: &'a DeviceControls) -> Option<Element<'a, Message>> {
+        let spacing = theme::active().cosmic().spacing;
+        let reason = control.disabled_reason()?;
+        let text = match reason {
+            DisabledReason::ReadOnly => fl!("disabled-read-only"),
+            DisabledReason::Disabled => fl!("disabled-by-driver"),
+            DisabledReason::Inactive => fl!("disabled-inactive"),
+        };
+        let row = widget::row()
+            .align_y(Alignment::Center)
+            .spacing(spacing.space_xs)
+            .push(widget::icon::from_name("dialog-information-symbolic").size(16).icon());
+        let gate = match reason {
+            DisabledReason::Inactive => find_gating_control(&dev.controls, control.id()),
+            _ => None,
+        };
+        let Some((master, _)) = gate else {
+            return Some(row.push(widget::text::caption(text)).into());
+        };
+        let row = row.push(widget::text::caption(fl!("disabled-by-control", control = master.name())));
+        let row = if master.is_disabled() {
+            row
+        } else {
+            row.push(
+                widget::button::link(fl!("switch-to-manual"))
+                    .on_press(Message::SwitchToManual(control.id()))
+            )
+        };
+        Some(row.into())
+    }
+
+    /// Pinned controls in the order they were pinned.
+    fn favorite_controls<'a>(&'a self, dev: &'a VideoDevice) -> Vec<&'a DeviceControls> {
+        self.favorites.iter()
+            .filter_map(|id| find_control(&dev.controls, *id))
+            .collect()
+    }
+
+    fn favorites_popover<'a>(&'a self, dev: &'a VideoDevice) -> Element<'a, Message> {
+        let spacing = theme::active().cosmic().spacing;
+        let button = widget::button::standard(fl!("favorites"))
+            .on_press(Message::ToggleFavoritesPopover);
+        let popover = widget::popover(button).on_close(Message::ToggleFavoritesPopover);
+        if !self.show_favorites_popover {
+            return popover.into();
+        }
+        let favorites = self.favorite_controls(dev);
+        let content: Element<Message> = if favorites.is_empty() {
+            widget::text::text(fl!("no-favorites")).into()
+        } else {
+            favorites.into_iter()
+                .fold(widget::column().spacing(spacing.space_xs), |column, control| {
+                    column.push(self.control_widget(dev, control))
+                })
+                .into()
+        };
+        popover.popup(
+            widget::container(content)
+                .padding(spacing.space_s)
+                .width(Length::Fixed(400.0))
+                .class(theme::Container::Dropdown)
+        ).into()
+    }
+
+    /// Lists the other linked cameras, flagging those where a change didn't land as asked.
+    fn link_status(&self) -> Element<Message> {
+        let spacing = theme::active().cosmic().spacing;
+        if self.links.is_empty() {
+            return widget::text::text(fl!("no-linked-cameras")).into();
+        }
+        self.links.iter().fold(widget::column().spacing(spacing.space_xxs), |column, link| {
+            let (icon, status) = if link.deviations.is_empty() {
+                ("emblem-ok-symbolic", fl!("link-in-sync"))
+            } else {
+                let deviations: Vec<String> = link.deviations.iter()
+                    .map(|(control, deviation)| format!("{}: {}", control, deviation))
+                    .collect();
+                ("dialog-warning-symbolic", deviations.join(", "))
+            };
+            column.push(
+                widget::row()
+                    .align_y(Alignment::Center)
+                    .spacing(spacing.space_xs)
+                    .push(widget::icon::from_name(icon).size(16).icon())
+                    .push(widget::text::text(link.name.clone()))
+                    .push(widget::text::caption(status))
+            )
+        }).into()
+    }
+
+    fn snapshot_row(&self) -> Element<Message> {
+        let spacing = theme::active().cosmic().spacing;
+        let button = widget::button::standard(fl!("take-snapshot"));
+        let button = if self.taking_snapshot {
+            button
+        } else {
+            button.on_press(Message::TakeSnapshot)
+        };
+        // While streaming, the device is busy and the latest frame is saved as is.
+        let size: Element<Message> = if self.is_streaming() {
+            widget::text::caption(fl!("snapshot-preview-size")).into()
+        } else {
+            widget::dropdown(
+                &self.snapshot_size_labels,
+                Some(self.snapshot_size),
+                Message::SnapshotSize,
+            ).into()
+        };
+        let row = widget::row()
+            .align_y(Alignment::Center)
+            .spacing(spacing.space_s)
+            .push(button)
+            .push(widget::dropdown(
+                &SnapshotFormat::ALL,
+                SnapshotFormat::ALL.iter().position(|format| *format == self.snapshot_format),
+                Message::SnapshotFormat,
+            ))
+            .push(size);
+        match &self.snapshot_status {
+            Some(status) => row.push(widget::text::caption(status.clone())).into(),
+            None => row.into(),
+        }
+    }
+
+    fn recording_row(&self) -> Element<Message> {
+        let spacing = theme::active().cosmic().spacing;
+        let button = if self.recording {
+            widget::button::destructive(fl!("stop-recording"))
+        } else {
+            widget::button::standard(fl!("start-recording"))
+        }.on_press(Message::ToggleRecording);
+        let format = widget::dropdown(
+            &RecordingFormat::ALL,
+            RecordingFormat::ALL.iter().position(|format| *format == self.recording_format),
+            Message::RecordingFormat,
+        );
+        let row = widget::row()
+            .align_y(Alignment::Center)
+            .spacing(spacing.space_s)
+            .push(button)
+            .push(format);
+        let status = match (&self.recording_error, &self.recording_stats) {
+            (Some(err), _) => Some(fl!("recording-failed", error = err.as_str())),
+            (None, Some(stats)) => {
+                let seconds = stats.elapsed.as_secs();
+                Some(fl!(
+                    "recording-stats",
+                    duration = format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60),
+                    size = format_size(stats.bytes),
+                    frames = stats.frames.to_string(),
+                    output = stats.output.display().to_string()
+                ))
+            }
+            _ => None,
+        };
+        match status {
+            Some(status) => row.push(widget::text::caption(status)).into(),
+            None => row.into(),
+        }
+    }
+
+    fn virtual_camera_controls(&self) -> Element<Message> {
+        let spacing = theme::active().cosmic().spacing;
+        if self.outputs.is_empty() {
+            return widget::text::caption(fl!("no-virtual-outputs")).into();
+        }
+        let header = widget::row()
+            .align_y(Alignment::Center)
+            .spacing(spacing.space_s)
+            .push(
+                widget::toggler(self.virtual_camera)
+                    .label(fl!("virtual-camera"))
+                    .on_toggle(Message::ToggleVirtualCamera)
+            )
+            .push(widget::dropdown(&self.output_labels, Some(self.output), Message::VirtualOutput));
+        let header = match &self.output_error {
+            Some(err) => header.push(widget::text::caption(fl!("virtual-camera-failed", error = err.as_str()))),
+            None => header,
+        };
+        if !self.virtual_camera {
+            return header.into();
+        }
+
+        let transform = self.transform;
+        let slider = |label: String, element: Element<'static, Message>| {
+            widget::row()
+                .align_y(Alignment::Center)
+                .spacing(spacing.space_s)
+                .push(widget::text::text(label).width(Length::Fixed(120.0)))
+                .push(element)
+        };
+        widget::column()
+            .spacing(spacing.space_xxs)
+            .push(header)
+            .push(slider(
+                fl!("transform-zoom"),
+                widgets::custom_slider(100, 400, transform.zoom, 5, false, Message::Zoom).into(),
+            ))
+            .push(slider(
+                fl!("transform-pan"),
+                widgets::custom_slider(-100, 100, transform.pan_x, 1, transform.zoom == 100, Message::PanX).into(),
+            ))
+            .push(slider(
+                fl!("transform-tilt"),
+                widgets::custom_slider(-100, 100, transform.pan_y, 1, transform.zoom == 100, Message::PanY).into(),
+            ))
+            .push(slider(
+                fl!("transform-brightness"),
+                widgets::custom_slider(-100, 100, transform.brightness, 1, false, Message::TransformBrightness).into(),
+            ))
+            .push(slider(
+                fl!("transform-contrast"),
+                widgets::custom_slider(50, 200, transform.contrast, 1, false, Message::TransformContrast).into(),
+            ))
+            .push(
+                widget::row()
+                    .align_y(Alignment::Center)
+                    .spacing(spacing.space_s)
+                    .push(widget::text::text(fl!("transform-rotation")))
+                    .push(widget::dropdown(
+                        &Rotation::ALL,
+                        Rotation::ALL.iter().position(|rotation| *rotation == transform.rotation),
+                        Message::Rotation,
+                    ))
+                    .push(
+                        widget::checkbox(fl!("transform-mirror"), transform.mirror)
+                            .on_toggle(Message::TransformMirror)
+                    )
+                    .push(
+                        widget::button::standard(fl!("reset-transform"))
+                            .on_press(Message::ResetTransform)
+                    )
+            )
+            .into()
+    }
+
+    /// A joystick for pan and tilt, a zoom rocker and the absolute position.
+    /// Directions are held down to move at speed, or clicked to step.
+    fn ptz_pad<'a>(&'a self, pad: Ptz<'a>) -> Element<'a, Message> {
+        let spacing = theme::active().cosmic().spacing;
+        let key = |icon: &'static str, axis: Axis, direction: i64| -> Element<'a, Message> {
+            let face = widget::container(widget::icon::from_name(icon).size(24).icon())
+                .padding(spacing.space_xs)
+                .class(theme::Container::Card);
+            if !pad.can_move(axis) {
+                return face.into();
+            }
+            mouse_area(face)
+                .on_press(Message::PtzStart(axis, direction))
+                .on_release(Message::PtzStop(axis))
+                .on_exit(Message::PtzStop(axis))
+                .into()
+        };
+        let joystick = widget::column()
+            .align_x(Alignment::Center)
+            .spacing(spacing.space_xxs)
+            .push(key("go-up-symbolic", Axis::Tilt, 1))
+            .push(
+                widget::row()
+                    .align_y(Alignment::Center)
+                    .spacing(spacing.space_xxs)
+                    .push(key("go-previous-symbolic", Axis::Pan, -1))
+                    .push(
+                        widget::button::icon(widget::icon::from_name("go-home-symbolic"))
+                            .tooltip(fl!("ptz-home"))
+                            .on_press(Message::PtzHome)
+                    )
+                    .push(key("go-next-symbolic", Axis::Pan, 1))
+            )
+            .push(key("go-down-symbolic", Axis::Tilt, -1));
+        let zoom = widget::column()
+            .align_x(Alignment::Center)
+            .spacing(spacing.space_xxs)
+            .push(key("zoom-in-symbolic", Axis::Zoom, 1))
+            .push(widget::text::caption(fl!("ptz-zoom")))
+            .push(key("zoom-out-symbolic", Axis::Zoom, -1));
+        let readout = [(Axis::Pan, fl!("ptz-pan")), (Axis::Tilt, fl!("ptz-tilt")), (Axis::Zoom, fl!("ptz-zoom"))]
+            .into_iter()
+            .filter_map(|(axis, label)| {
+                let control = pad.absolute(axis)?;
+                Some(format!("{} {}", label, self.shown_value(control.id, control.value)))
+            })
+            .fold(widget::column().spacing(spacing.space_xxs), |column, line| {
+                column.push(widget::text::text(line))
+            });
+        widget::column()
+            .spacing(spacing.space_xxs)
+            .push(widget::text::title4(fl!("ptz")))
+            .push(
+                widget::row()
+                    .align_y(Alignment::Center)
+                    .spacing(spacing.space_l)
+                    .push(joystick)
+                    .push(zoom)
+                    .push(readout)
+            )
+            .push(self.presets())
+            .push(self.calibration_row(pad))
+            .into()
+    }
+
+    fn calibration_row<'a>(&'a self, pad: Ptz<'a>) -> Element<'a, Message> {
+        let spacing = theme::active().cosmic().spacing;
+        let button = widget::button::standard(fl!("calibrate-aim"));
+        let can_calibrate = self.show_preview && self.calibration.is_none() && pad.absolute(Axis::Pan).is_some();
+        let button = if can_calibrate { button.on_press(Message::Calibrate) } else { button };
+        let status = match (&self.calibration, &self.calibration_status) {
+            (Some(_), _) => fl!("calibrating"),
+            (None, Some(status)) => status.clone(),
+            (None, None) => fl!("aim-hint", fov = format!("{:.0}", self.fov)),
+        };
+        widget::row()
+            .align_y(Alignment::Center)
+            .spacing(spacing.space_s)
+            .push(button)
+            .push(widget::text::caption(status))
+            .into()
+    }
+
+    /// Moves the calibration along with each frame: the first is compared
+    /// against once the camera has panned and settled, then it pans back.
+    fn calibration_step(&mut self, dev: &VideoDevice) -> Option<Task> {
+        let calibration = self.calibration.take()?;
+        let pad = Ptz::find(&dev.controls)?;
+        let pan = pad.absolute(Axis::Pan)?;
+        let zoom = pad.absolute(Axis::Zoom).map(|zoom| self.shown_value(zoom.id, zoom.value));
+        let (profile, width) = {
+            let frame = self.last_frame.as_ref()?;
+            (ptz::column_profile(frame), frame.width)
+        };
+        match calibration {
+            Calibration::Start => {
+                let current = self.shown_value(pan.id, pan.value);
+                let Some((target, degrees)) = pad.calibration_move(self.fov, current, zoom) else {
+                    self.calibration_status = Some(fl!("calibration-failed"));
+                    return None;
+                };
+                self.calibration = Some(Calibration::Moving {
+                    pan: current,
+                    degrees,
+                    before: profile,
+                    until: Instant::now() + ptz::CALIBRATION_SETTLE,
+                });
+                self.request_unlinked_write(pan.id, ControlValue::Integer(target))
+            }
+            Calibration::Moving { pan: from, degrees, before, until } => {
+                if Instant::now() < until {
+                    self.calibration = Some(Calibration::Moving { pan: from, degrees, before, until });
+                    return None;
                 }
-                _ => form.push(widget::text::text("No Widget"))
+                let back = self.request_unlinked_write(pan.id, ControlValue::Integer(from));
+                let fov = ptz::estimate_shift(&before, &profile, width)
+                    .and_then(|shift| pad.calibrated_fov(degrees, shift, width, zoom));
+                let Some(fov) = fov else {
+                    self.calibration_status = Some(fl!("calibration-failed"));
+                    return back;
+                };
+                self.fov = fov;
+                self.calibration_status = Some(fl!("calibrated", fov = format!("{:.1}", fov)));
+                Some(Task::Batch(back.into_iter().chain([Task::SaveFov(fov)]).collect()))
+            }
+        }
+    }
+
+    /// Saves changed auto settings; turning a part on switches the camera's own automation off again.
+    fn update_auto(&mut self, auto: AutoSettings) -> Option<Task> {
+        let turned_on = (auto.exposure && !self.auto.exposure) || (auto.white_balance && !self.auto.white_balance);
+        if turned_on {
+            self.auto_loop.reset();
+        }
+        self.auto = auto;
+        Some(Task::SaveAuto(auto))
+    }
+
+    /// Runs the software auto exposure and white balance on the latest frame.
+    fn auto_step(&mut self, dev: &VideoDevice) -> Option<Task> {
+        // The assistants set exposure, white balance and focus themselves.
+        if self.flicker_detection.is_some()
+            || self.white_balance.is_some()
+            || self.focus_sweep.is_some()
+            || self.calibration.is_some()
+        {
+            return None;
+        }
+        let frame = self.last_frame.as_ref()?;
+        // Taken out for the update, which reads the shown values off `self`.
+        let mut auto_loop = std::mem::take(&mut self.auto_loop);
+        let values = auto_loop.update(&self.auto, frame, &dev.controls, |control| {
+            self.shown_value(control.id, control.value)
+        });
+        self.auto_loop = auto_loop;
+        self.request_unlinked_values(values)
+    }
+
+    /// Toggles for the software auto exposure and white balance, with their
+    /// targets and tuning while either is on.
+    fn auto_controls(&self) -> Element<Message> {
+        let spacing = theme::active().cosmic().spacing;
+        let auto = self.auto;
+        let header = widget::row()
+            .align_y(Alignment::Center)
+            .spacing(spacing.space_s)
+            .push(widget::text::text(fl!("software-auto")))
+            .push(widget::checkbox(fl!("auto-exposure"), auto.exposure).on_toggle(Message::ToggleAutoExposure))
+            .push(
+                widget::checkbox(fl!("auto-white-balance"), auto.white_balance)
+                    .on_toggle(Message::ToggleAutoWhiteBalance)
+            );
+        if !auto.is_active() {
+            return header.into();
+        }
+
+        let slider = |label: String, element: Element<'static, Message>| {
+            widget::row()
+                .align_y(Alignment::Center)
+                .spacing(spacing.space_s)
+                .push(widget::text::text(label).width(Length::Fixed(120.0)))
+                .push(element)
+        };
+        widget::column()
+            .spacing(spacing.space_xxs)
+            .push(header)
+            .push(widget::text::caption(fl!("software-auto-hint")))
+            .push(slider(
+                fl!("auto-target"),
+                widgets::custom_slider(5, 95, auto.target, 1, !auto.exposure, Message::AutoTarget).into(),
+            ))
+            .push(slider(
+                fl!("auto-max-exposure"),
+                widgets::custom_slider(1, 100, auto.max_exposure, 1, !auto.exposure, Message::AutoMaxExposure).into(),
+            ))
+            .push(slider(
+                fl!("auto-max-gain"),
+                widgets::custom_slider(0, 100, auto.max_gain, 1, !auto.exposure, Message::AutoMaxGain).into(),
+            ))
+            .push(slider(
+                fl!("auto-warmth"),
+                widgets::custom_slider(-50, 50, auto.warmth, 1, !auto.white_balance, Message::AutoWarmth).into(),
+            ))
+            .push(slider(
+                fl!("auto-speed"),
+                widgets::custom_slider(1, 100, auto.speed, 1, false, Message::AutoSpeed).into(),
+            ))
+            .push(slider(
+                fl!("auto-damping"),
+                widgets::custom_slider(0, 95, auto.damping, 1, false, Message::AutoDamping).into(),
+            ))
+            .into()
+    }
+
+    /// Detecting mains flicker, with the recommendation once done.
+    fn flicker_row<'a>(&'a self, dev: &'a VideoDevice) -> Option<Element<'a, Message>> {
+        find_control(&dev.controls, cid::POWER_LINE_FREQUENCY)?;
+        find_control(&dev.controls, cid::EXPOSURE_ABSOLUTE)?;
+        let spacing = theme::active().cosmic().spacing;
+        let button = match &self.flicker_detection {
+            Some(_) => widget::button::standard(fl!("stop-flicker")).on_press(Message::StopFlicker),
+            None => widget::button::standard(fl!("detect-flicker")).on_press(Message::DetectFlicker),
+        };
+        let status = match (&self.flicker_detection, self.flicker) {
+            (Some(detection), _) => fl!("detecting-flicker", percent = (detection.progress() * 100.0).round() as i64),
+            (None, Some(Flicker::Found(mains))) => fl!("flicker-found", frequency = mains.as_ref()),
+            (None, Some(Flicker::None)) => fl!("flicker-none"),
+            (None, Some(Flicker::Unclear)) => fl!("flicker-unclear"),
+            (None, None) => fl!("flicker-hint"),
+        };
+        let row = widget::row()
+            .align_y(Alignment::Center)
+            .spacing(spacing.space_s)
+            .push(button)
+            .push(widget::text::caption(status));
+        let row = match self.flicker {
+            Some(Flicker::Found(mains)) if self.flicker_detection.is_none() => row.push(
+                widget::button::suggested(fl!("use-frequency", frequency = mains.as_ref()))
+                    .on_press(Message::UseMains(mains))
+            ),
+            _ => row,
+        };
+        Some(row.into())
+    }
+
+    /// Moves flicker detection along with each frame, putting the controls back once done.
+    fn flicker_step(&mut self) -> Option<Task> {
+        let mut detection = self.flicker_detection.take()?;
+        match detection.frame(self.last_frame.as_ref()?) {
+            DetectStep::Wait => {
+                self.flicker_detection = Some(detection);
+                None
+            }
+            DetectStep::Write(values) => {
+                self.flicker_detection = Some(detection);
+                self.request_unlinked_writes(values)
             }
-        })
-            .push(widget::button::standard(fl!("save"))
-                .on_press(Message::Save)
-                .padding([spacing.space_xxs, spacing.space_s])
+            DetectStep::Done(flicker, restore) => {
+                self.flicker = Some(flicker);
+                // Restoring may turn the camera's auto exposure back on.
+                self.auto_loop.reset();
+                self.request_unlinked_writes(restore)
+            }
+        }
+    }
+
+    /// Where the pointer is over the preview, from -0.5 to 0.5 across the
+    /// captured frame. The mirrored preview is flipped, the camera isn't.
+    fn preview_point(&self) -> Option<(f32, f32)> {
+        let point = self.preview_cursor?;
+        let frame = self.last_frame.as_ref()?;
+        let aspect = frame.width as f32 / frame.height.max(1) as f32;
+        let x = point.x / PREVIEW_WIDTH - 0.5;
+        let y = point.y / (PREVIEW_WIDTH / aspect) - 0.5;
+        Some((if self.guides.mirror { -x } else { x }, y))
+    }
+
+    /// Picking a neutral point to set white balance from.
+    fn white_balance_row<'a>(&'a self, dev: &'a VideoDevice) -> Option<Element<'a, Message>> {
+        let manual = [cid::WHITE_BALANCE_TEMPERATURE, cid::RED_BALANCE, cid::BLUE_BALANCE];
+        if !manual.iter().any(|id| find_control(&dev.controls, *id).is_some()) {
+            return None;
+        }
+        let spacing = theme::active().cosmic().spacing;
+        let button = widget::button::standard(fl!("pick-white"));
+        let button = if self.white_balance.is_none() { button.on_press(Message::PickWhite) } else { button };
+        let status = match (self.picking_white, &self.white_balance, &self.white_balance_status) {
+            (true, _, _) => fl!("pick-white-hint"),
+            (_, Some(_), _) => fl!("setting-white-balance"),
+            (_, None, Some(status)) => status.clone(),
+            (_, None, None) => String::new(),
+        };
+        Some(
+            widget::row()
+                .align_y(Alignment::Center)
+                .spacing(spacing.space_s)
+                .push(button)
+                .push(widget::text::caption(status))
+                .into()
+        )
+    }
+
+    /// Moves white balance toward neutral at the picked point with each frame.
+    fn white_balance_step(&mut self) -> Option<Task> {
+        let mut balance = self.white_balance.take()?;
+        match balance.frame(self.last_frame.as_ref()?) {
+            BalanceStep::Wait => {
+                self.white_balance = Some(balance);
+                None
+            }
+            BalanceStep::Write(values) => {
+                self.white_balance = Some(balance);
+                self.request_unlinked_writes(values)
+            }
+            BalanceStep::Done(neutral) => {
+                let value = match balance.balance() {
+                    Balance::Temperature(kelvin) => fl!("white-balance-temperature", value = kelvin),
+                    Balance::RedBlue(red, blue) => fl!("white-balance-red-blue", red = red, blue = blue),
+                };
+                self.white_balance_status = Some(match neutral {
+                    true => fl!("white-balance-set", value = value),
+                    false => fl!("white-balance-closest", value = value),
+                });
+                None
+            }
+        }
+    }
+
+    /// The focus sweep, with the sharpness curve of the last one.
+    fn focus_assistant<'a>(&'a self, dev: &'a VideoDevice) -> Option<Element<'a, Message>> {
+        find_control(&dev.controls, cid::FOCUS_ABSOLUTE)?;
+        let spacing = theme::active().cosmic().spacing;
+        let button = match &self.focus_sweep {
+            Some(_) => widget::button::standard(fl!("stop-focus")).on_press(Message::StopFocus),
+            None => widget::button::standard(fl!("find-focus")).on_press(Message::FindFocus),
+        };
+        let status = match (&self.focus_sweep, &self.focus_status) {
+            (Some(sweep), _) => fl!("finding-focus", percent = (sweep.progress() * 100.0).round() as i64),
+            (None, Some(status)) => status.clone(),
+            (None, None) => fl!("focus-hint"),
+        };
+        let row = widget::row()
+            .align_y(Alignment::Center)
+            .spacing(spacing.space_s)
+            .push(button)
+            .push(widget::text::caption(fl!("focus-region")))
+            .push(widget::dropdown(
+                &FocusRegion::ALL,
+                FocusRegion::ALL.iter().position(|region| *region == self.focus_region),
+                Message::FocusRegion,
+            ))
+            .push(widget::text::caption(status));
+        let column = widget::column()
+            .spacing(spacing.space_xxs)
+            .push(widget::text::title4(fl!("focus-assistant")))
+            .push(row);
+        let column = match &self.focus_curve {
+            Some(curve) => column.push(widget::image(curve.clone())),
+            None => column,
+        };
+        Some(column.into())
+    }
+
+    /// Moves the focus sweep along with each frame and settles on the sharpest position when done.
+    fn focus_step(&mut self, dev: &VideoDevice) -> Option<Task> {
+        let mut sweep = self.focus_sweep.take()?;
+        let DeviceControls::Integer(focus) = find_control(&dev.controls, cid::FOCUS_ABSOLUTE)? else {
+            return None;
+        };
+        let step = sweep.frame(self.last_frame.as_ref()?, self.focus_region);
+        match step {
+            SweepStep::Wait => {
+                self.focus_sweep = Some(sweep);
+                None
+            }
+            SweepStep::Move(position) => {
+                self.focus_sweep = Some(sweep);
+                self.request_unlinked_write(focus.id, ControlValue::Integer(position))
+            }
+            SweepStep::Done(best) => {
+                let (width, height, pixels) = focus::curve_image(sweep.scores(), best);
+                self.focus_curve = Some(widget::image::Handle::from_rgba(width, height, pixels));
+                self.focus_status = Some(fl!("focus-found", value = best));
+                self.request_unlinked_write(focus.id, ControlValue::Integer(best))
+            }
+        }
+    }
+
+    /// Saved positions, recalled by clicking them or pressing their number.
+    fn presets(&self) -> Element<Message> {
+        let spacing = theme::active().cosmic().spacing;
+        let save = widget::row()
+            .align_y(Alignment::Center)
+            .spacing(spacing.space_s)
+            .push(
+                widget::text_input(fl!("preset-name"), &self.preset_name)
+                    .on_input(Message::PresetName)
+                    .on_submit(Message::SavePreset)
+                    .width(Length::Fixed(200.0))
             )
+            .push(widget::button::standard(fl!("save-preset")).on_press(Message::SavePreset));
+        let presets = self.presets.iter().enumerate().fold(
+            widget::row().spacing(spacing.space_xs).align_y(Alignment::Center),
+            |row, (i, preset)| {
+                let label = match i {
+                    0..=8 => format!("{}. {}", i + 1, preset.name),
+                    _ => preset.name.clone(),
+                };
+                row.push(widget::button::standard(label).on_press(Message::RecallPreset(i)))
+                    .push(
+                        widget::button::icon(widget::icon::from_name("edit-delete-symbolic"))
+                            .extra_small()
+                            .tooltip(fl!("delete-preset"))
+                            .on_press(Message::DeletePreset(i))
+                    )
+            },
+        );
+        widget::column()
+            .spacing(spacing.space_xxs)
+            .push(save)
+            .push(presets)
+            .into()
+    }
+
+    fn scope_toggles(&self) -> Element<Message> {
+        let spacing = theme::active().cosmic().spacing;
+        ScopeKind::ALL.iter().fold(
+            widget::row()
+                .align_y(Alignment::Center)
+                .spacing(spacing.space_s)
+                .push(widget::text::text(fl!("scopes"))),
+            |row, kind| {
+                let kind = *kind;
+                row.push(
+                    widget::checkbox(kind.title(), self.scopes.contains(&kind))
+                        .on_toggle(move |_| Message::ToggleScope(kind))
+                )
+            },
+        ).into()
+    }
+
+    /// Measured against negotiated frame rate, lost frames and a graph of the
+    /// time between frames, to tell a slow camera or bus from a slow app.
+    fn stream_stats(&self) -> Element<Message> {
+        let spacing = theme::active().cosmic().spacing;
+        let toggle = widget::checkbox(fl!("stream-stats"), self.show_stats).on_toggle(Message::ToggleStats);
+        if !self.show_stats {
+            return toggle.into();
+        }
+        let column = widget::column().spacing(spacing.space_xxs).push(toggle);
+        let Some(stats) = self.stream_stats.as_ref().filter(|_| self.is_streaming()) else {
+            return column.push(widget::text::caption(fl!("stream-stats-idle"))).into();
+        };
+        let column = column
+            .push(widget::text::text(fl!(
+                "stream-fps",
+                fps = format!("{:.1}", stats.fps),
+                nominal = format!("{:.1}", stats.nominal_fps)
+            )))
+            .push(widget::text::text(fl!(
+                "stream-frames",
+                frames = stats.frames,
+                dropped = stats.dropped,
+                skipped = stats.skipped,
+                late = stats.late
+            )))
+            .push(widget::text::caption(fl!("stream-jitter", jitter = format!("{:.1}", stats.jitter))));
+        match &self.stats_graph {
+            Some(graph) => column.push(widget::image(graph.clone())).into(),
+            None => column.into(),
+        }
+    }
+
+    fn scopes(&self) -> Element<Message> {
+        let spacing = theme::active().cosmic().spacing;
+        self.scopes.iter().fold(widget::column().spacing(spacing.space_xs), |column, kind| {
+            let image: Element<Message> = match self.scope_images.iter().find(|(k, _)| k == kind) {
+                Some((_, handle)) => widget::image(handle.clone())
+                    .width(Length::Fixed(SCOPE_WIDTH))
+                    .into(),
+                None => widget::text::caption(fl!("scopes-waiting")).into(),
+            };
+            column
+                .push(widget::text::title4(kind.title()))
+                .push(image)
+        }).into()
+    }
+
+    fn overlay_controls(&self) -> Element<Message> {
+        let spacing = theme::active().cosmic().spacing;
+        let overlays = self.overlays;
+        let zebra = widget::row()
+            .align_y(Alignment::Center)
+            .spacing(spacing.space_s)
+            .push(widget::checkbox(fl!("zebra"), overlays.zebra).on_toggle(Message::ToggleZebra))
+            .push(
+                widgets::custom_slider(50, 100, overlays.zebra_level, 1, !overlays.zebra, Message::ZebraLevel)
+                    .width(Length::Fixed(160.0))
+            )
+            .push(widget::text::caption(fl!("zebra-level", level = overlays.zebra_level.to_string())));
+        let peaking = widget::row()
+            .align_y(Alignment::Center)
+            .spacing(spacing.space_s)
+            .push(widget::checkbox(fl!("focus-peaking"), overlays.peaking).on_toggle(Message::TogglePeaking))
+            .push(
+                // Inverted, so that moving right marks more edges.
+                widgets::custom_slider(10, 120, 130 - overlays.peaking_level, 1, !overlays.peaking, |x| {
+                    Message::PeakingLevel(130 - x)
+                })
+                    .width(Length::Fixed(160.0))
+            )
+            .push(widget::text::caption(fl!("peaking-sensitivity")))
+            .push(widget::dropdown(
+                &PeakingColor::ALL,
+                PeakingColor::ALL.iter().position(|color| *color == overlays.peaking_color),
+                Message::PeakingColor,
+            ));
+        let guides = self.guides;
+        let framing = widget::row()
+            .align_y(Alignment::Center)
+            .spacing(spacing.space_s)
+            .push(widget::checkbox(fl!("guide-thirds"), guides.thirds).on_toggle(Message::ToggleThirds))
+            .push(widget::checkbox(fl!("guide-center"), guides.center).on_toggle(Message::ToggleCenter))
+            .push(widget::checkbox(fl!("guide-safe-area"), guides.safe_area).on_toggle(Message::ToggleSafeArea))
+            .push(widget::text::text(fl!("guide-aspect")))
+            .push(widget::dropdown(
+                &self.aspect_labels,
+                Some(guides.aspect.map_or(0, |aspect| {
+                    AspectRatio::ALL.iter().position(|a| *a == aspect).map_or(0, |i| i + 1)
+                })),
+                Message::AspectMask,
+            ))
+            .push(widget::checkbox(fl!("mirror-preview"), guides.mirror).on_toggle(Message::ToggleMirror));
+        widget::column()
+            .spacing(spacing.space_xxs)
+            .push(zebra)
+            .push(peaking)
+            .push(framing)
+            .into()
+    }
+
+    /// Redraws the preview from the latest frame, with the overlays.
+    fn update_preview(&mut self) {
+        let Some(live) = &self.last_frame else {
+            return;
+        };
+        let split = self.reference.as_ref()
+            .and_then(|reference| compare::split(&reference.frame, live, self.wipe, self.guides.mirror));
+        let frame = split.as_ref().unwrap_or(live);
+        let mut pixels = if self.overlays.is_active() {
+            self.overlays.apply(frame)
+        } else {
+            frame.pixels.clone()
+        };
+        if self.guides.is_active() {
+            self.guides.apply(frame.width, frame.height, &mut pixels);
+        }
+        self.preview = Some(widget::image::Handle::from_rgba(frame.width, frame.height, pixels));
+    }
+
+    /// Puts back the values the device had before the reference values were applied.
+    fn restore_live_values(&mut self, dev: &VideoDevice) -> Option<Task> {
+        let values = self.live_values_before_reference.take()?;
+        Some(Task::WriteMany(to_control_values(&dev.controls, &values)))
+    }
+
+    /// Puts back what was only changed for a while, before the device or the
+    /// preview goes away: the live values under the reference values, and
+    /// those flicker detection changed.
+    pub fn restore_temporary_values(&mut self, dev: &VideoDevice) -> Option<Task> {
+        let mut values = self.live_values_before_reference.take().unwrap_or_default();
+        if let Some(detection) = self.flicker_detection.take() {
+            let restore: Vec<(u32, i64)> = detection.into_restore().into_iter()
+                .filter(|(id, _)| !values.iter().any(|(live, _)| live == id))
+                .collect();
+            values.extend(restore);
+        }
+        (!values.is_empty()).then(|| Task::WriteMany(to_control_values(&dev.controls, &values)))
+    }
+
+    /// The values of the device's writable controls as shown, see `get_current_values`.
+    fn current_values(&self, dev: &VideoDevice) -> Vec<(u32, i64)> {
+        get_current_values(&dev.controls).into_iter()
+            .map(|(id, value)| (id, self.shown_value(id, value)))
+            .collect()
+    }
+
+    /// Freezing a reference frame to compare against, and switching the device
+    /// between the reference control values and the live ones.
+    fn compare_row(&self) -> Element<Message> {
+        let spacing = theme::active().cosmic().spacing;
+        if self.reference.is_none() {
+            return widget::row()
+                .align_y(Alignment::Center)
+                .spacing(spacing.space_s)
+                .push(widget::button::standard(fl!("freeze-reference")).on_press(Message::FreezeReference))
+                .push(widget::text::caption(fl!("freeze-reference-hint")))
+                .into();
+        }
+        let applied = self.live_values_before_reference.is_some();
+        widget::row()
+            .align_y(Alignment::Center)
+            .spacing(spacing.space_s)
+            .push(widget::button::standard(fl!("clear-reference")).on_press(Message::ClearReference))
+            .push(widget::text::caption(fl!("wipe")))
+            .push(widgets::custom_slider(0, 100, self.wipe, 1, false, Message::Wipe).width(Length::Fixed(160.0)))
+            .push(
+                widget::toggler(applied)
+                    .label(fl!("reference-values"))
+                    .on_toggle(Message::ApplyReference)
+            )
+            .push(widget::text::caption(if applied { fl!("showing-a") } else { fl!("showing-b") }))
+            .into()
+    }
+
+    fn update_guides(&mut self, guides: Guides) -> Option<Task> {
+        self.guides = guides;
+        self.update_preview();
+        Some(Task::SaveGuides(guides))
+    }
+
+    /// The latest frame. On PTZ cameras, clicking a point aims at it and
+    /// scrolling zooms.
+    fn preview<'a>(&'a self, dev: &'a VideoDevice) -> Element<'a, Message> {
+        let image = match (&self.preview, &self.preview_error) {
+            (_, Some(err)) => return widget::text::text(fl!("preview-failed", error = err.as_str())).into(),
+            (Some(handle), _) => widget::image(handle.clone()).width(Length::Fixed(PREVIEW_WIDTH)),
+            _ => return widget::text::text(fl!("preview-starting")).into(),
+        };
+        let aimable = Ptz::find(&dev.controls).is_some_and(|pad| {
+            [Axis::Pan, Axis::Tilt, Axis::Zoom].iter().any(|axis| pad.absolute(*axis).is_some())
+        });
+        if !aimable && !self.picking_white {
+            return image.into();
+        }
+        mouse_area(image)
+            .on_move(|point| Message::PreviewCursor(Some(point)))
+            .on_exit(Message::PreviewCursor(None))
+            .on_press(if self.picking_white { Message::SetWhite } else { Message::AimAtCursor })
+            .on_scroll(Message::PreviewScroll)
+            .interaction(cosmic::iced::mouse::Interaction::Crosshair)
             .into()
     }
 
     pub fn view<'a>(&'a self, dev: &'a VideoDevice) -> Element<Message> {
         let spacing = theme::active().cosmic().spacing;
-        widget::scrollable(widget::column()
+        let controls = widget::scrollable(widget::column()
             .spacing(spacing.space_xs)
             .push(self.title())
             .push(self.device_controls(dev))
-        ).into()
+        )
+            .id(self.scroll_id.clone())
+            .on_scroll(Message::Scrolled)
+            .width(Length::Fill);
+        if self.scopes.is_empty() {
+            return controls.into();
+        }
+        widget::row()
+            .spacing(spacing.space_s)
+            .push(controls)
+            .push(widget::scrollable(self.scopes()).width(Length::Shrink))
+            .into()
     }
 
     pub fn update(&mut self, dev: &VideoDevice, message: Message) -> Option<Task> {
         match message {
             Message::None => None,
-            Message::Save => Some(Task::Save),
-            Message::Slider(id, val) => {
-                set_control_val(dev, id, v4l::control::Value::Integer(val as i64)).unwrap();
+            Message::Filter(filter) => {
+                let cleared = !self.filter.is_empty() && filter.is_empty();
+                self.filter = filter;
+                if cleared {
+                    return Some(Task::ScrollTo(self.scroll_id.clone(), self.scroll_offset));
+                }
                 None
-            },
-            Message::Boolean(id, val) => {
-                set_control_val(dev, id, v4l::control::Value::Boolean(val)).unwrap();
+            }
+            Message::ToggleFavorite(id) => {
+                match self.favorites.iter().position(|favorite| *favorite == id) {
+                    Some(position) => {
+                        self.favorites.remove(position);
+                    }
+                    None => self.favorites.push(id),
+                }
+                Some(Task::SaveFavorites(self.favorites.clone()))
+            }
+            Message::ToggleLink => Some(Task::ToggleLink),
+            Message::Poll => {
+                let mut ids = get_live_controls(&dev.controls);
+                if let Some(pad) = Ptz::find(&dev.controls).filter(|_| self.is_ptz_moving()) {
+                    ids.extend(self.ptz_moving.iter().filter_map(|axis| Some(pad.absolute(*axis)?.id)));
+                }
+                if self.polling || ids.is_empty() {
+                    return None;
+                }
+                self.polling = true;
+                Some(Task::Poll(ids))
+            }
+            Message::Polled(result) => {
+                self.polling = false;
+                match result {
+                    Ok(values) => self.live_values.extend(values),
+                    Err(err) => println!("Could not poll controls: {}", err),
+                }
                 None
             }
-            Message::Menu(id, val) => {
-                set_control_val(dev, id, v4l::control::Value::Integer(val as i64)).unwrap();
-
+            Message::SwitchToManual(id) => {
+                let (master, gate) = find_gating_control(&dev.controls, id)?;
+                self.request_write(master.id(), (gate.manual)())
+            }
+            Message::ToggleFavoritesPopover => {
+                self.show_favorites_popover = !self.show_favorites_popover;
                 None
             }
-            Message::ButtonPress(id) => {
-                set_control_val(dev, id, v4l::control::Value::None).unwrap();
+            Message::FavoritesLoaded(favorites) => {
+                self.favorites = favorites;
+                None
+            }
+            Message::Scrolled(viewport) => {
+                if self.filter.is_empty() {
+                    self.scroll_offset = viewport.absolute_offset();
+                }
                 None
+            }
+            Message::Save => Some(Task::Save(self.profile.clone())),
+            Message::Saved => {
+                self.profiles = storage::get_profiles(dev);
+                None
+            }
+            Message::Integer(id, val) => {
+                self.focused_control = Some(id);
+                self.drafts.remove(&id);
+                let Some(DeviceControls::Integer(control)) = find_control(&dev.controls, id) else {
+                    return None;
+                };
+                if control.is_disabled() {
+                    return None;
+                }
+                let val = control.snap(val);
+                if val == self.shown_value(id, control.value) {
+                    return None;
+                }
+                self.request_write(id, ControlValue::Integer(val))
             },
-            Message::OpenDialog(dialog) => {
-                self.open_dialog = dialog;
+            Message::IntegerInput(id, text) => {
+                self.focused_control = Some(id);
+                self.drafts.insert(id, text);
+                None
+            }
+            Message::IntegerSubmit(id) => {
+                match self.drafts.get(&id).map(|text| text.trim().parse::<i64>()) {
+                    Some(Ok(val)) => self.update(dev, Message::Integer(id, val)),
+                    _ => {
+                        self.drafts.remove(&id);
+                        None
+                    }
+                }
+            }
+            Message::Nudge(id, nudge) => {
+                let Some(DeviceControls::Integer(control)) = find_control(&dev.controls, id) else {
+                    return None;
+                };
+                let current = self.shown_value(id, control.value);
+                let val = match nudge {
+                    Nudge::Up => control.nudge(current, 1, false),
+                    Nudge::Down => control.nudge(current, -1, false),
+                    Nudge::PageUp => control.nudge(current, 1, true),
+                    Nudge::PageDown => control.nudge(current, -1, true),
+                };
+                self.update(dev, Message::Integer(id, val))
+            }
+            Message::NudgeFocused(nudge) => {
+                let id = self.focused_entry?;
+                self.update(dev, Message::Nudge(id, nudge))
+            }
+            Message::EntryFocus(id, focused) => {
+                if focused {
+                    self.focused_control = Some(id);
+                    self.focused_entry = Some(id);
+                } else if self.focused_entry == Some(id) {
+                    self.focused_entry = None;
+                }
                 None
+            }
+            Message::Boolean(id, val) => {
+                self.focused_control = Some(id);
+                self.request_write(id, ControlValue::Boolean(val))
+            }
+            Message::Menu(id, val) => {
+                self.focused_control = Some(id);
+                self.request_write(id, ControlValue::Integer(val as i64))
+            }
+            Message::ButtonPress(id) => {
+                self.focused_control = Some(id);
+                self.request_write(id, ControlValue::None)
             },
+            Message::Written(id, result) => {
+                self.in_flight.remove(&id);
+                if let Err(err) = result {
+                    println!("Could not set control {}: {}", id, err);
+                }
+                match self.pending.remove(&id) {
+                    Some((value, linked)) => self.write_values(vec![(id, value)], linked),
+                    None => Some(Self::read_back_task(vec![id])),
+                }
+            }
+            Message::WrittenMany(ids, result) => {
+                if let Err(err) = result {
+                    println!("Could not set every control: {}", err);
+                }
+                Some(Self::read_back_task(ids))
+            }
+            Message::ReadBack(result) => {
+                match result {
+                    Ok(values) => {
+                        for (id, value) in values {
+                            if !self.in_flight.contains(&id) && !self.pending.contains_key(&id) {
+                                self.requested.remove(&id);
+                            }
+                            self.read_back.insert(id, value);
+                        }
+                    }
+                    Err(err) => println!("Could not read back controls: {}", err),
+                }
+                None
+            }
+            Message::ShowInfo => Some(Task::ShowInfo),
+            Message::ProfileName(name) => {
+                self.profile = name;
+                None
+            }
+            Message::SelectProfile(index) => {
+                let profile = self.profiles.get(index)?.clone();
+                self.profile = profile.clone();
+                Some(Task::LoadProfile(profile))
+            }
+            Message::NextProfile => {
+                let next = self.profiles.iter()
+                    .position(|p| *p == self.profile)
+                    .map_or(0, |i| (i + 1) % self.profiles.len());
+                self.update(dev, Message::SelectProfile(next))
+            }
+            Message::ProfileLoaded(Ok(save_data)) => {
+                if let Some(transform) = save_data.transform {
+                    self.set_transform(transform);
+                }
+                Some(Task::WriteMany(get_saved_values(dev, &save_data)))
+            }
+            Message::ProfileLoaded(Err(err)) => {
+                println!("Could not load profile: {}", err);
+                None
+            }
+            Message::ResetControl => {
+                let control = find_control(&dev.controls, self.focused_control?)?;
+                let value = control.default_value()?;
+                self.request_write(control.id(), value)
+            }
+            Message::ResetGroup => {
+                let group = find_control_group(&dev.controls, self.focused_control?)?;
+                Some(Task::WriteMany(get_default_values(&group.controls)))
+            }
+            Message::ResetDevice => {
+                Some(Task::WriteMany(get_default_values(&dev.controls)))
+            }
+            Message::TogglePreview => {
+                self.show_preview = !self.show_preview;
+                self.preview = None;
+                self.preview_error = None;
+                self.last_frame = None;
+                // The A/B toggle and flicker detection go away with the preview.
+                match self.show_preview {
+                    true => None,
+                    false => self.restore_temporary_values(dev),
+                }
+            }
+            Message::TakeSnapshot => {
+                // The size picker is hidden while streaming, as the frame comes at the stream's size.
+                let (frame, size) = match self.is_streaming() {
+                    true => (Some(self.last_frame.clone()?), None),
+                    false => (None, self.snapshot_sizes.get(self.snapshot_size).copied().flatten()),
+                };
+                self.taking_snapshot = true;
+                self.snapshot_status = None;
+                Some(Task::Snapshot(SnapshotRequest {
+                    size,
+                    format: self.snapshot_format,
+                    frame,
+                    folder: None,
+                }))
+            }
+            Message::SnapshotFormat(index) => {
+                self.snapshot_format = *SnapshotFormat::ALL.get(index)?;
+                None
+            }
+            Message::SnapshotSize(index) => {
+                self.snapshot_size = index.min(self.snapshot_sizes.len() - 1);
+                None
+            }
+            Message::FrameSizes(Ok(sizes)) => {
+                self.snapshot_sizes = std::iter::once(None)
+                    .chain(sizes.iter().map(|size| Some(*size)))
+                    .collect();
+                self.snapshot_size_labels = std::iter::once(fl!("current-size"))
+                    .chain(sizes.iter().map(|(width, height)| format!("{}×{}", width, height)))
+                    .collect();
+                self.snapshot_size = 0;
+                None
+            }
+            Message::FrameSizes(Err(err)) => {
+                println!("Could not list frame sizes: {}", err);
+                None
+            }
+            Message::SnapshotSaved(result) => {
+                self.taking_snapshot = false;
+                self.snapshot_status = Some(match result {
+                    Ok(filename) => fl!("snapshot-saved", filename = filename),
+                    Err(err) => fl!("snapshot-failed", error = err),
+                });
+                None
+            }
+            Message::Stream(stream::Event::Frame(frame)) => {
+                self.last_frame = Some(frame);
+                if self.show_preview {
+                    self.update_preview();
+                }
+                let tasks: Vec<Task> = [self.calibration_step(dev), self.focus_step(dev), self.white_balance_step(), self.flicker_step(), self.auto_step(dev)]
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                (!tasks.is_empty()).then_some(Task::Batch(tasks))
+            }
+            Message::PreviewCursor(point) => {
+                self.preview_cursor = point;
+                None
+            }
+            Message::AimAtCursor => {
+                let (x, y) = self.preview_point()?;
+                let frame = self.last_frame.as_ref()?;
+                let aspect = frame.width as f32 / frame.height.max(1) as f32;
+                let pad = Ptz::find(&dev.controls)?;
+                let values = pad.aim(self.fov, aspect, (x, y), |control| self.shown_value(control.id, control.value));
+                self.request_unlinked_writes(values)
+            }
+            Message::PreviewScroll(delta) => {
+                let lines = match delta {
+                    ScrollDelta::Lines { y, .. } | ScrollDelta::Pixels { y, .. } => y,
+                };
+                if lines == 0.0 {
+                    return None;
+                }
+                let pad = Ptz::find(&dev.controls)?;
+                let (id, value) = pad.scroll_zoom(lines, |control| self.shown_value(control.id, control.value))?;
+                self.request_write(id, ControlValue::Integer(value))
+            }
+            Message::Calibrate => {
+                if !self.show_preview {
+                    return None;
+                }
+                self.calibration = Some(Calibration::Start);
+                self.calibration_status = None;
+                None
+            }
+            Message::FindFocus => {
+                let DeviceControls::Integer(focus) = find_control(&dev.controls, cid::FOCUS_ABSOLUTE)? else {
+                    return None;
+                };
+                if !self.show_preview {
+                    return None;
+                }
+                self.focus_sweep = Some(Sweep::new(focus));
+                self.focus_curve = None;
+                self.focus_status = None;
+                // Autofocus would fight the sweep, so it stays off afterwards too.
+                match find_control(&dev.controls, cid::FOCUS_AUTO) {
+                    Some(DeviceControls::Boolean(auto)) if self.shown_value(auto.id, auto.value as i64) != 0 => {
+                        self.request_unlinked_write(auto.id, ControlValue::Boolean(false))
+                    }
+                    _ => None,
+                }
+            }
+            Message::StopFocus => {
+                self.focus_sweep = None;
+                self.focus_status = Some(fl!("focus-stopped"));
+                None
+            }
+            Message::FocusRegion(index) => {
+                self.focus_region = *FocusRegion::ALL.get(index)?;
+                None
+            }
+            Message::ToggleAutoExposure(exposure) => self.update_auto(AutoSettings { exposure, ..self.auto }),
+            Message::ToggleAutoWhiteBalance(white_balance) => {
+                self.update_auto(AutoSettings { white_balance, ..self.auto })
+            }
+            Message::AutoTarget(target) => self.update_auto(AutoSettings { target, ..self.auto }),
+            Message::AutoWarmth(warmth) => self.update_auto(AutoSettings { warmth, ..self.auto }),
+            Message::AutoSpeed(speed) => self.update_auto(AutoSettings { speed, ..self.auto }),
+            Message::AutoDamping(damping) => self.update_auto(AutoSettings { damping, ..self.auto }),
+            Message::AutoMaxExposure(max_exposure) => self.update_auto(AutoSettings { max_exposure, ..self.auto }),
+            Message::AutoMaxGain(max_gain) => self.update_auto(AutoSettings { max_gain, ..self.auto }),
+            Message::DetectFlicker => {
+                if !self.show_preview || self.flicker_detection.is_some() {
+                    return None;
+                }
+                let (detection, prepare) = Detection::new(&dev.controls, |id, value| self.shown_value(id, value))?;
+                self.flicker_detection = Some(detection);
+                self.flicker = None;
+                self.request_unlinked_writes(prepare)
+            }
+            Message::StopFlicker => {
+                let detection = self.flicker_detection.take()?;
+                // Restoring may turn the camera's auto exposure back on.
+                self.auto_loop.reset();
+                self.request_unlinked_writes(detection.into_restore())
+            }
+            Message::UseMains(mains) => {
+                self.request_write(cid::POWER_LINE_FREQUENCY, ControlValue::Integer(mains.menu_value()))
+            }
+            Message::PickWhite => {
+                self.picking_white = !self.picking_white && self.show_preview;
+                self.white_balance = None;
+                self.white_balance_status = None;
+                None
+            }
+            Message::SetWhite => {
+                self.picking_white = false;
+                let (x, y) = self.preview_point()?;
+                let balance = Neutralize::new(&dev.controls, (x + 0.5, y + 0.5), |control| {
+                    self.shown_value(control.id, control.value)
+                });
+                let Some(balance) = balance else {
+                    self.white_balance_status = Some(fl!("white-balance-unsupported"));
+                    return None;
+                };
+                self.white_balance = Some(balance);
+                // Automatic white balance would undo every write.
+                match find_control(&dev.controls, cid::AUTO_WHITE_BALANCE) {
+                    Some(DeviceControls::Boolean(auto)) if self.shown_value(auto.id, auto.value as i64) != 0 => {
+                        self.request_unlinked_write(auto.id, ControlValue::Boolean(false))
+                    }
+                    _ => None,
+                }
+            }
+            Message::ToggleZebra(zebra) => {
+                self.overlays.zebra = zebra;
+                self.update_preview();
+                None
+            }
+            Message::ZebraLevel(level) => {
+                self.overlays.zebra_level = level;
+                self.update_preview();
+                None
+            }
+            Message::TogglePeaking(peaking) => {
+                self.overlays.peaking = peaking;
+                self.update_preview();
+                None
+            }
+            Message::PeakingLevel(level) => {
+                self.overlays.peaking_level = level;
+                self.update_preview();
+                None
+            }
+            Message::PeakingColor(index) => {
+                self.overlays.peaking_color = *PeakingColor::ALL.get(index)?;
+                self.update_preview();
+                None
+            }
+            Message::Stream(stream::Event::Stats(stats)) => {
+                if self.show_stats {
+                    let (width, height, pixels) = stats.graph();
+                    self.stats_graph = Some(widget::image::Handle::from_rgba(width, height, pixels));
+                }
+                self.stream_stats = Some(stats);
+                None
+            }
+            Message::FreezeReference => {
+                let frame = self.last_frame.clone()?;
+                self.reference = Some(Reference { frame, values: self.current_values(dev) });
+                self.update_preview();
+                None
+            }
+            Message::ClearReference => {
+                self.reference = None;
+                self.update_preview();
+                self.restore_live_values(dev)
+            }
+            Message::Wipe(wipe) => {
+                self.wipe = wipe;
+                self.update_preview();
+                None
+            }
+            Message::ApplyReference(true) => {
+                if self.live_values_before_reference.is_some() {
+                    return None;
+                }
+                let writes = to_control_values(&dev.controls, &self.reference.as_ref()?.values);
+                self.live_values_before_reference = Some(self.current_values(dev));
+                Some(Task::WriteMany(writes))
+            }
+            Message::ApplyReference(false) => self.restore_live_values(dev),
+            Message::ToggleStats(show) => {
+                self.show_stats = show;
+                self.stats_graph = None;
+                None
+            }
+            Message::Stream(stream::Event::Recording(stats)) => {
+                if self.recording {
+                    self.recording_stats = Some(stats);
+                }
+                None
+            }
+            Message::Stream(stream::Event::RecordingError(err)) => {
+                self.recording = false;
+                self.recording_error = Some(err);
+                None
+            }
+            Message::Stream(stream::Event::OutputError(err)) => {
+                self.virtual_camera = false;
+                self.output_error = Some(err);
+                None
+            }
+            Message::Stream(stream::Event::Error(err)) => {
+                if self.recording {
+                    self.recording = false;
+                    self.recording_error = Some(err.clone());
+                }
+                self.preview_error = Some(err);
+                None
+            }
+            Message::ToggleVirtualCamera(on) => {
+                self.virtual_camera = on;
+                self.output_error = None;
+                None
+            }
+            Message::VirtualOutput(index) => {
+                self.output = index.min(self.outputs.len().saturating_sub(1));
+                self.output_error = None;
+                None
+            }
+            Message::OutputsLoaded(Ok(outputs)) => {
+                // Feeding a loopback node from itself would go nowhere.
+                let outputs: Vec<(String, String)> = outputs.into_iter()
+                    .filter(|(path, _)| *path != dev.path)
+                    .collect();
+                self.output_labels = outputs.iter().map(|(path, name)| format!("{} ({})", name, path)).collect();
+                self.outputs = outputs.into_iter().map(|(path, _)| path).collect();
+                self.output = 0;
+                None
+            }
+            Message::OutputsLoaded(Err(err)) => {
+                println!("Could not list virtual camera outputs: {}", err);
+                None
+            }
+            Message::TransformLoaded(transform) => {
+                self.set_transform(transform.unwrap_or_default());
+                None
+            }
+            Message::Zoom(zoom) => {
+                self.set_transform(Transform { zoom, ..self.transform });
+                None
+            }
+            Message::PanX(pan_x) => {
+                self.set_transform(Transform { pan_x, ..self.transform });
+                None
+            }
+            Message::PanY(pan_y) => {
+                self.set_transform(Transform { pan_y, ..self.transform });
+                None
+            }
+            Message::Rotation(index) => {
+                let rotation = *Rotation::ALL.get(index)?;
+                self.set_transform(Transform { rotation, ..self.transform });
+                None
+            }
+            Message::TransformMirror(mirror) => {
+                self.set_transform(Transform { mirror, ..self.transform });
+                None
+            }
+            Message::TransformBrightness(brightness) => {
+                self.set_transform(Transform { brightness, ..self.transform });
+                None
+            }
+            Message::TransformContrast(contrast) => {
+                self.set_transform(Transform { contrast, ..self.transform });
+                None
+            }
+            Message::ResetTransform => {
+                self.set_transform(Transform::default());
+                None
+            }
+            Message::PtzStart(axis, direction) => {
+                let pad = Ptz::find(&dev.controls)?;
+                let (id, value) = pad.start(axis, direction, |control| self.shown_value(control.id, control.value))?;
+                if pad.stop(axis).is_some() {
+                    self.ptz_moving.insert(axis);
+                }
+                self.request_write(id, value)
+            }
+            Message::PtzStop(axis) => {
+                if !self.ptz_moving.remove(&axis) {
+                    return None;
+                }
+                let (id, value) = Ptz::find(&dev.controls)?.stop(axis)?;
+                self.request_write(id, value)
+            }
+            Message::PtzHome => Some(Task::WriteMany(Ptz::find(&dev.controls)?.home())),
+            Message::PresetsLoaded(presets) => {
+                self.presets = presets;
+                None
+            }
+            Message::PresetName(name) => {
+                self.preset_name = name;
+                None
+            }
+            Message::SavePreset => {
+                let values: Vec<(u32, i64)> = ptz::PRESET_CONTROLS.iter()
+                    .filter_map(|id| match find_control(&dev.controls, *id) {
+                        Some(DeviceControls::Integer(control)) if !control.is_disabled() => {
+                            Some((control.id, self.shown_value(control.id, control.value)))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                if values.is_empty() {
+                    return None;
+                }
+                let name = match self.preset_name.trim() {
+                    "" => fl!("preset-default-name", number = (self.presets.len() + 1).to_string()),
+                    name => name.to_string(),
+                };
+                let preset = Preset { name, values };
+                match self.presets.iter_mut().find(|p| p.name == preset.name) {
+                    Some(existing) => *existing = preset,
+                    None => self.presets.push(preset),
+                }
+                self.preset_name.clear();
+                Some(Task::SavePresets(self.presets.clone()))
+            }
+            Message::DeletePreset(index) => {
+                if index >= self.presets.len() {
+                    return None;
+                }
+                self.presets.remove(index);
+                Some(Task::SavePresets(self.presets.clone()))
+            }
+            Message::RecallPreset(index) => {
+                let to = self.presets.get(index)?.values.clone();
+                if self.preset_transition.is_zero() {
+                    self.transition = None;
+                    return self.request_unlinked_writes(to);
+                }
+                let from = to.iter()
+                    .filter_map(|(id, _)| match find_control(&dev.controls, *id) {
+                        Some(DeviceControls::Integer(control)) => Some((*id, self.shown_value(*id, control.value))),
+                        _ => None,
+                    })
+                    .collect();
+                self.transition = Some(Transition::new(from, to, self.preset_transition));
+                self.update(dev, Message::TransitionTick)
+            }
+            Message::TransitionTick => {
+                let (values, done) = self.transition.as_ref()?.step();
+                if done {
+                    self.transition = None;
+                }
+                self.request_unlinked_writes(values)
+            }
+            Message::ToggleScope(kind) => {
+                if self.scopes.contains(&kind) {
+                    self.scopes.retain(|k| *k != kind);
+                    self.scope_images.retain(|(k, _)| *k != kind);
+                } else {
+                    self.scopes.push(kind);
+                    self.scopes.sort_by_key(|k| ScopeKind::ALL.iter().position(|all| all == k));
+                }
+                None
+            }
+            Message::UpdateScopes => {
+                if self.computing_scopes || self.scopes.is_empty() {
+                    return None;
+                }
+                let frame = self.last_frame.clone()?;
+                self.computing_scopes = true;
+                Some(Task::Scopes(frame, self.scopes.clone()))
+            }
+            Message::ScopesComputed(result) => {
+                self.computing_scopes = false;
+                match result {
+                    Ok(images) => {
+                        self.scope_images = images.into_iter()
+                            .filter(|image| self.scopes.contains(&image.kind))
+                            .map(|image| {
+                                let handle = widget::image::Handle::from_rgba(image.width, image.height, image.pixels);
+                                (image.kind, handle)
+                            })
+                            .collect();
+                    }
+                    Err(err) => println!("Could not compute scopes: {}", err),
+                }
+                None
+            }
+            Message::ToggleThirds(thirds) => self.update_guides(Guides { thirds, ..self.guides }),
+            Message::ToggleCenter(center) => self.update_guides(Guides { center, ..self.guides }),
+            Message::ToggleSafeArea(safe_area) => self.update_guides(Guides { safe_area, ..self.guides }),
+            Message::AspectMask(index) => {
+                let aspect = index.checked_sub(1).and_then(|i| AspectRatio::ALL.get(i).copied());
+                self.update_guides(Guides { aspect, ..self.guides })
+            }
+            Message::ToggleMirror(mirror) => self.update_guides(Guides { mirror, ..self.guides }),
+            Message::ToggleRecording => {
+                self.recording = !self.recording;
+                self.recording_error = None;
+                if self.recording {
+                    self.recording_stats = None;
+                }
+                None
+            }
+            Message::RecordingFormat(index) => {
+                if !self.recording {
+                    self.recording_format = *RecordingFormat::ALL.get(index)?;
+                }
+                None
+            }
         }
     }
 }
+
+/// Formats a byte count like `12.3 MB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} {}", bytes, UNITS[0]),
+        _ => format!("{:.1} {}", size, UNITS[unit]),
+    }
+}
@@ -1,11 +1,13 @@
 use std::collections::HashMap;
 use std::convert::AsRef;
+use std::sync::Arc;
 
 use v4l::context;
 use v4l::prelude::*;
 use v4l::control::Type as ControlType;
 use v4l::control::Value as ControlValue;
 
+use crate::cid;
 use crate::storage::SaveData;
 
 
@@ -25,8 +27,43 @@ fn disabled_flags() -> v4l::control::Flags {
 }
 
 
+/// Why a control can't be changed, most specific first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisabledReason {
+    ReadOnly,
+    Disabled,
+    /// Usually an automatic mode of another control is on.
+    Inactive,
+}
+
 pub trait ControlData {
     fn is_disabled(&self) -> bool;
+    fn flags(&self) -> v4l::control::Flags;
+
+    /// Whether the hardware may change the value on its own, so it is worth
+    /// polling: volatile and read-only values, and those an auto mode drives.
+    fn is_live(&self) -> bool {
+        let flags = self.flags();
+        !flags.contains(v4l::control::Flags::DISABLED) && flags.intersects(
+            v4l::control::Flags::VOLATILE |
+            v4l::control::Flags::READ_ONLY |
+            v4l::control::Flags::INACTIVE
+        )
+    }
+
+    fn disabled_reason(&self) -> Option<DisabledReason> {
+        if !self.is_disabled() {
+            return None;
+        }
+        let flags = self.flags();
+        if flags.contains(v4l::control::Flags::READ_ONLY) {
+            Some(DisabledReason::ReadOnly)
+        } else if flags.contains(v4l::control::Flags::DISABLED) {
+            Some(DisabledReason::Disabled)
+        } else {
+            Some(DisabledReason::Inactive)
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -47,6 +84,10 @@ impl ControlData for Control {
     fn is_disabled(&self) -> bool {
         self.flags.intersects(disabled_flags())
     }
+
+    fn flags(&self) -> v4l::control::Flags {
+        self.flags
+    }
 }
 
 #[derive(Debug)]
@@ -65,6 +106,36 @@ impl ControlData for IntegerControl {
     fn is_disabled(&self) -> bool {
         self.flags.intersects(disabled_flags())
     }
+
+    fn flags(&self) -> v4l::control::Flags {
+        self.flags
+    }
+}
+
+impl IntegerControl {
+    /// Clamps a value to the control's range and rounds it to the nearest step.
+    /// Works in i128 so 64-bit ranges can't overflow.
+    pub fn snap(&self, value: i64) -> i64 {
+        let min = self.min as i128;
+        let step = self.step.max(1) as i128;
+        let value = value.clamp(self.min, self.max) as i128;
+        let snapped = min + (value - min + step / 2) / step * step;
+        snapped.min(self.max as i128) as i64
+    }
+
+    /// Roughly a tenth of the range, rounded to whole steps.
+    pub fn coarse_step(&self) -> i64 {
+        let step = self.step.max(1) as i128;
+        let range = self.max as i128 - self.min as i128;
+        ((range / step / 10).max(1) * step).min(i64::MAX as i128) as i64
+    }
+
+    /// Moves a value by a number of steps, fine or coarse.
+    pub fn nudge(&self, value: i64, steps: i64, coarse: bool) -> i64 {
+        let step = if coarse { self.coarse_step() } else { self.step.max(1) as i64 };
+        let value = value as i128 + steps as i128 * step as i128;
+        self.snap(value.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+    }
 }
 
 #[derive(Debug)]
@@ -80,6 +151,10 @@ impl ControlData for BooleanControl {
     fn is_disabled(&self) -> bool {
         self.flags.intersects(disabled_flags())
     }
+
+    fn flags(&self) -> v4l::control::Flags {
+        self.flags
+    }
 }
 
 #[derive(Debug)]
@@ -96,6 +171,10 @@ impl ControlData for MenuControl {
     fn is_disabled(&self) -> bool {
         self.flags.intersects(disabled_flags())
     }
+
+    fn flags(&self) -> v4l::control::Flags {
+        self.flags
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -123,6 +202,10 @@ impl ControlData for ButtonControl {
     fn is_disabled(&self) -> bool {
         self.flags.intersects(disabled_flags())
     }
+
+    fn flags(&self) -> v4l::control::Flags {
+        self.flags
+    }
 }
 
 #[derive(Debug)]
@@ -152,36 +235,83 @@ pub fn get_devices() -> Vec<VideoDevice> {
                 .capabilities
                 .contains(v4l::capability::Flags::META_CAPTURE)
         })
-        .filter_map(|dev| {
-        let name = dev.name().unwrap_or(String::from("Unknown"));
-        let path = dev.path().to_str();
-        let device = path.as_ref().and_then(|p| get_v4l_device_by_path(p).ok());
-        let device_controls = device.as_ref().and_then(|d| get_device_controls(d).ok());
-
-        if let (name, Some(path), Some(device_controls)) = (name, path, device_controls) {
-            Some(VideoDevice {
-                name: name.to_string(),
-                path: path.to_string(),
-                capabilities: get_capabilities(path),
-                index: dev.index(),
-                controls: device_controls,
-            })
-        } else {
-            None
-        }
-        })
+        .filter_map(get_video_device)
         .collect::<Vec<VideoDevice>>();
     devices
 }
 
+fn get_video_device(dev: &context::Node) -> Option<VideoDevice> {
+    let name = dev.name().unwrap_or(String::from("Unknown"));
+    let path = dev.path().to_str();
+    let device = path.as_ref().and_then(|p| get_v4l_device_by_path(p).ok());
+    let device_controls = device.as_ref().and_then(|d| get_device_controls(d).ok());
+
+    if let (name, Some(path), Some(device_controls)) = (name, path, device_controls) {
+        Some(VideoDevice {
+            name: name.to_string(),
+            path: path.to_string(),
+            capabilities: get_capabilities(path),
+            index: dev.index(),
+            controls: device_controls,
+        })
+    } else {
+        None
+    }
+}
+
 pub fn get_device_by_path(path: &str) -> Result<VideoDevice, String> {
-    let dev = get_devices().into_iter().find(|dev| dev.path == path);
+    let dev = context::enum_devices()
+        .iter()
+        .find(|dev| dev.path().to_str() == Some(path))
+        .and_then(get_video_device);
     match dev {
         Some(device) => Ok(device),
         None => Err(format!("Device not found: {}", path)),
     }
 }
 
+/// Runs blocking device I/O on tokio's blocking pool, so a slow ioctl never
+/// holds up the UI thread.
+pub async fn blocking<T, F>(f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await.map_err(|e| format!("{}", e))?
+}
+
+pub async fn load_device(path: String) -> Result<Arc<VideoDevice>, String> {
+    blocking(move || get_device_by_path(&path).map(Arc::new)).await
+}
+
+pub async fn read_save_data(dev: Arc<VideoDevice>) -> Result<SaveData, String> {
+    blocking(move || get_device_save_data(&dev)).await
+}
+
+/// Reads the current values of the given controls, booleans as 0 and 1.
+pub fn get_control_vals(path: &str, control_ids: &[u32]) -> Result<Vec<(u32, i64)>, String> {
+    let dev = get_v4l_device_by_path(path)?;
+    Ok(control_ids.iter().filter_map(|id| {
+        match dev.control(*id).ok()?.value {
+            ControlValue::Integer(val) => Some((*id, val)),
+            ControlValue::Boolean(val) => Some((*id, val as i64)),
+            _ => None,
+        }
+    }).collect())
+}
+
+pub async fn read_controls(path: String, control_ids: Vec<u32>) -> Result<Vec<(u32, i64)>, String> {
+    blocking(move || get_control_vals(&path, &control_ids)).await
+}
+
+pub async fn write_control(dev: Arc<VideoDevice>, control_id: u32, value: ControlValue) -> Result<(), String> {
+    blocking(move || set_control_val(&dev, control_id, value)).await
+}
+
+pub async fn write_controls(dev: Arc<VideoDevice>, values: Vec<(u32, ControlValue)>) -> Result<(), String> {
+    blocking(move || set_control_vals(&dev, values)).await
+}
+
 pub fn get_capabilities(path: &str) -> v4l::capability::Capabilities {
     let dev = Device::with_path(path).unwrap();
     dev.query_caps().expect("Failed to query capabilities")
@@ -205,9 +335,9 @@ pub fn get_device_controls(dev: &Device) -> Result<Vec<DeviceControls>, String>
                     flags: ctrl.flags,
                 }));
             }
-            ControlType::Integer => {
-                let ctrl_val = match dev.control(ctrl.id).map_err(|e| format!("{}", e))?.value {
-                    ControlValue::Integer(val) => val,
+            ControlType::Integer | ControlType::Integer64 => {
+                let ctrl_val = match dev.control(ctrl.id).map(|control| control.value) {
+                    Ok(ControlValue::Integer(val)) => val,
                     _ => {
                         println!("Could not get control value for integer");
                         ctrl.default
@@ -231,8 +361,8 @@ pub fn get_device_controls(dev: &Device) -> Result<Vec<DeviceControls>, String>
                 }
             }
             ControlType::Boolean => {
-                let ctrl_val = match dev.control(ctrl.id).map_err(|e| format!("{}", e))?.value {
-                    ControlValue::Boolean(val) => val,
+                let ctrl_val = match dev.control(ctrl.id).map(|control| control.value) {
+                    Ok(ControlValue::Boolean(val)) => val,
                     _ => {
                         println!("Could not get control value for boolean");
                         ctrl.default != 0
@@ -252,20 +382,22 @@ pub fn get_device_controls(dev: &Device) -> Result<Vec<DeviceControls>, String>
                     _ => device_controls.push(current_ctrl),
                 }
             },
-            ControlType::Menu => {
-                let ctrl_val = match dev.control(ctrl.id).map_err(|e| format!("{}", e))?.value {
-                    ControlValue::Integer(val) => val,
+            ControlType::Menu | ControlType::IntegerMenu => {
+                let ctrl_val = match dev.control(ctrl.id).map(|control| control.value) {
+                    Ok(ControlValue::Integer(val)) => val,
                     _ => {
                         println!("Could not get control value for menu");
                         ctrl.default
                     },
                 };
+                // Integer menus list plain numbers, such as link frequencies.
+                let integer_menu = matches!(ctrl.typ, ControlType::IntegerMenu);
                 let menu_items: Vec<MenuItem> = match &ctrl.items {
                     Some(items) => {
                     items.iter().map(|item| {
                         let label = match &item.1 {
-                            v4l::control::MenuItem::Value(0) => "Off".to_string(),
-                            v4l::control::MenuItem::Value(1) => "On".to_string(),
+                            v4l::control::MenuItem::Value(0) if !integer_menu => "Off".to_string(),
+                            v4l::control::MenuItem::Value(1) if !integer_menu => "On".to_string(),
                             v4l::control::MenuItem::Name(name) => name.to_string(),
                             v4l::control::MenuItem::Value(val) => val.to_string(),
                         };
@@ -359,10 +491,10 @@ pub fn get_device_save_data(device: &VideoDevice) -> Result<SaveData, String> {
         if let Ok(ctrl) = dev.control(ctrl.id).map_err(|e| format!("{}", e)) {
                 match ctrl.value {
                     ControlValue::Integer(val) => {
-                        control_values.insert(ctrl.id, val as u32);
+                        control_values.insert(ctrl.id, val);
                     } 
                     ControlValue::Boolean(val) => {
-                        control_values.insert(ctrl.id, val as u32);
+                        control_values.insert(ctrl.id, val as i64);
                     }
                     _ => ()
                 }
@@ -371,6 +503,7 @@ pub fn get_device_save_data(device: &VideoDevice) -> Result<SaveData, String> {
     Ok(
         SaveData {
             controls: control_values,
+            transform: None,
         }
     )
 }
@@ -384,3 +517,331 @@ pub fn set_control_val(dev: &VideoDevice, control_id: u32, value: ControlValue)
     dev.set_control(control).map_err(|e| format!("{}", e))?;
     Ok(())
 }
+
+impl ControlData for DeviceControls {
+    fn is_disabled(&self) -> bool {
+        match self {
+            DeviceControls::ControlGroup(_) => false,
+            DeviceControls::Integer(control) => control.is_disabled(),
+            DeviceControls::Boolean(control) => control.is_disabled(),
+            DeviceControls::Control(control) => control.is_disabled(),
+            DeviceControls::Menu(control) => control.is_disabled(),
+            DeviceControls::Button(control) => control.is_disabled(),
+        }
+    }
+
+    fn flags(&self) -> v4l::control::Flags {
+        match self {
+            DeviceControls::ControlGroup(group) => group.flags,
+            DeviceControls::Integer(control) => control.flags,
+            DeviceControls::Boolean(control) => control.flags,
+            DeviceControls::Control(control) => control.flags,
+            DeviceControls::Menu(control) => control.flags,
+            DeviceControls::Button(control) => control.flags,
+        }
+    }
+}
+
+/// An automatic mode that makes other controls inactive while it is on.
+pub struct Gate {
+    pub master: u32,
+    /// The value that switches the master control to manual.
+    pub manual: fn() -> ControlValue,
+}
+
+/// The master control that gates a control, for the controls we know about.
+pub fn get_gate(control_id: u32) -> Option<Gate> {
+    let manual_menu = || ControlValue::Integer(cid::EXPOSURE_MANUAL);
+    let off = || ControlValue::Boolean(false);
+    let (master, manual): (u32, fn() -> ControlValue) = match control_id {
+        cid::EXPOSURE | cid::EXPOSURE_ABSOLUTE => (cid::EXPOSURE_AUTO, manual_menu),
+        cid::WHITE_BALANCE_TEMPERATURE | cid::RED_BALANCE | cid::BLUE_BALANCE => {
+            (cid::AUTO_WHITE_BALANCE, off)
+        }
+        cid::FOCUS_ABSOLUTE | cid::FOCUS_RELATIVE => (cid::FOCUS_AUTO, off),
+        cid::GAIN => (cid::AUTOGAIN, off),
+        cid::HUE => (cid::HUE_AUTO, off),
+        _ => return None,
+    };
+    Some(Gate { master, manual })
+}
+
+/// Finds the master control on the device that makes the given control inactive.
+pub fn find_gating_control(controls: &[DeviceControls], control_id: u32) -> Option<(&DeviceControls, Gate)> {
+    let gate = get_gate(control_id)?;
+    let master = find_control(controls, gate.master)?;
+    Some((master, gate))
+}
+
+/// Whether the control is an automatic mode that gates others, so writing it
+/// changes which controls are active.
+pub fn is_gate_master(control_id: u32) -> bool {
+    [cid::EXPOSURE, cid::WHITE_BALANCE_TEMPERATURE, cid::FOCUS_ABSOLUTE, cid::GAIN, cid::HUE]
+        .iter()
+        .filter_map(|id| get_gate(*id))
+        .any(|gate| gate.master == control_id)
+}
+
+impl DeviceControls {
+    pub fn id(&self) -> u32 {
+        match self {
+            DeviceControls::ControlGroup(group) => group.id,
+            DeviceControls::Integer(control) => control.id,
+            DeviceControls::Boolean(control) => control.id,
+            DeviceControls::Control(control) => control.id,
+            DeviceControls::Menu(control) => control.id,
+            DeviceControls::Button(control) => control.id,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            DeviceControls::ControlGroup(group) => &group.name,
+            DeviceControls::Integer(control) => &control.name,
+            DeviceControls::Boolean(control) => &control.name,
+            DeviceControls::Control(control) => &control.name,
+            DeviceControls::Menu(control) => &control.name,
+            DeviceControls::Button(control) => &control.name,
+        }
+    }
+
+    /// A short name for the kind of control, as users would search for it.
+    pub fn kind(&self) -> String {
+        match self {
+            DeviceControls::ControlGroup(_) => String::from("class"),
+            DeviceControls::Integer(_) => String::from("integer"),
+            DeviceControls::Boolean(_) => String::from("boolean"),
+            DeviceControls::Control(control) => format!("{:?}", control.control_type).to_lowercase(),
+            DeviceControls::Menu(_) => String::from("menu"),
+            DeviceControls::Button(_) => String::from("button"),
+        }
+    }
+
+    /// Whether a lowercase search query matches the control's name or kind.
+    pub fn matches(&self, query: &str) -> bool {
+        self.name().to_lowercase().contains(query) || self.kind().contains(query)
+    }
+
+    /// The value a control falls back to when it is reset, or `None` for
+    /// controls that can't be written.
+    pub fn default_value(&self) -> Option<ControlValue> {
+        match self {
+            DeviceControls::Integer(control) if !control.is_disabled() => {
+                Some(ControlValue::Integer(control.default))
+            }
+            DeviceControls::Boolean(control) if !control.is_disabled() => {
+                Some(ControlValue::Boolean(control.default))
+            }
+            DeviceControls::Menu(control) if !control.is_disabled() => {
+                Some(ControlValue::Integer(control.default as i64))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Collects the default values of every writable control in the tree.
+pub fn get_default_values(controls: &[DeviceControls]) -> Vec<(u32, ControlValue)> {
+    controls.iter().flat_map(|control| match control {
+        DeviceControls::ControlGroup(group) => get_default_values(&group.controls),
+        control => control.default_value().map(|val| (control.id(), val)).into_iter().collect(),
+    }).collect()
+}
+
+/// Whether a control can be written at all. Unlike `is_disabled`, controls
+/// that are only inactive count, since their auto mode may be switched off first.
+fn is_writable(control: &DeviceControls) -> bool {
+    !control.flags().intersects(
+        v4l::control::Flags::READ_ONLY |
+        v4l::control::Flags::DISABLED |
+        v4l::control::Flags::WRITE_ONLY
+    )
+}
+
+/// Collects the current value of every control that can be written, booleans
+/// as 0 and 1 and auto modes first, so the controls they gate follow them.
+pub fn get_current_values(controls: &[DeviceControls]) -> Vec<(u32, i64)> {
+    fn collect(controls: &[DeviceControls], values: &mut Vec<(u32, i64)>) {
+        for control in controls {
+            let writable = is_writable(control);
+            match control {
+                DeviceControls::ControlGroup(group) => collect(&group.controls, values),
+                DeviceControls::Integer(control) if writable => values.push((control.id, control.value)),
+                DeviceControls::Boolean(control) if writable => values.push((control.id, control.value as i64)),
+                DeviceControls::Menu(control) if writable => {
+                    if let Some(value) = control.value {
+                        values.push((control.id, value as i64));
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+    let mut values = Vec::new();
+    collect(controls, &mut values);
+    values.sort_by_key(|(id, _)| !is_gate_master(*id));
+    values
+}
+
+/// Turns values from `get_current_values` back into writes, keeping their order.
+pub fn to_control_values(controls: &[DeviceControls], values: &[(u32, i64)]) -> Vec<(u32, ControlValue)> {
+    values.iter().filter_map(|(id, value)| match find_control(controls, *id)? {
+        DeviceControls::Boolean(_) => Some((*id, ControlValue::Boolean(*value != 0))),
+        DeviceControls::Integer(_) | DeviceControls::Menu(_) => Some((*id, ControlValue::Integer(*value))),
+        _ => None,
+    }).collect()
+}
+
+/// Finds the group that contains the control with the given id.
+pub fn find_control_group(controls: &[DeviceControls], control_id: u32) -> Option<&ControlGroup> {
+    controls.iter().find_map(|control| match control {
+        DeviceControls::ControlGroup(group)
+            if group.controls.iter().any(|control| control.id() == control_id) => Some(group),
+        _ => None,
+    })
+}
+
+/// Finds the control with the given id, looking inside groups.
+pub fn find_control(controls: &[DeviceControls], control_id: u32) -> Option<&DeviceControls> {
+    controls.iter().find_map(|control| match control {
+        DeviceControls::ControlGroup(group) => find_control(&group.controls, control_id),
+        control if control.id() == control_id => Some(control),
+        _ => None,
+    })
+}
+
+/// Ids of the controls worth polling, see [`ControlData::is_live`].
+pub fn get_live_controls(controls: &[DeviceControls]) -> Vec<u32> {
+    controls.iter().flat_map(|control| match control {
+        DeviceControls::ControlGroup(group) => get_live_controls(&group.controls),
+        DeviceControls::Integer(_) | DeviceControls::Boolean(_) | DeviceControls::Menu(_)
+            if control.is_live() => vec![control.id()],
+        _ => Vec::new(),
+    }).collect()
+}
+
+pub fn find_control_by_name<'a>(controls: &'a [DeviceControls], name: &str) -> Option<&'a DeviceControls> {
+    controls.iter().find_map(|control| match control {
+        DeviceControls::ControlGroup(group) => find_control_by_name(&group.controls, name),
+        control if control.name() == name => Some(control),
+        _ => None,
+    })
+}
+
+/// Maps saved values back onto the device controls, so booleans are written
+/// as booleans. Auto modes come first, so the controls they gate are active by
+/// the time they are written, whatever mode the camera is in now. Controls
+/// whose auto mode was saved on are left to it.
+pub fn get_saved_values(device: &VideoDevice, save_data: &SaveData) -> Vec<(u32, ControlValue)> {
+    let left_to_auto = |id: u32| get_gate(id).is_some_and(|gate| {
+        let manual = match (gate.manual)() {
+            ControlValue::Integer(val) => val,
+            ControlValue::Boolean(val) => val as i64,
+            _ => return false,
+        };
+        save_data.controls.get(&gate.master).is_some_and(|saved| *saved != manual)
+    });
+    let mut ids: Vec<u32> = save_data.controls.keys().copied().collect();
+    ids.sort_by_key(|id| (!is_gate_master(*id), *id));
+    ids.into_iter().filter_map(|id| {
+        let val = save_data.controls[&id];
+        match find_control(&device.controls, id) {
+            Some(control) if !is_writable(control) || left_to_auto(id) => None,
+            Some(DeviceControls::Boolean(_)) => Some((id, ControlValue::Boolean(val != 0))),
+            Some(DeviceControls::Integer(_) | DeviceControls::Menu(_)) => Some((id, ControlValue::Integer(val))),
+            _ => None,
+        }
+    }).collect()
+}
+
+/// Writes several controls, carrying on past failures so one stubborn control
+/// doesn't stop the rest. Returns the first error.
+pub fn set_control_vals(dev: &VideoDevice, values: Vec<(u32, ControlValue)>) -> Result<(), String> {
+    let dev = get_v4l_device_by_path(&dev.path).map_err(|e| format!("{}", e))?;
+    let mut result = Ok(());
+    for (id, value) in values {
+        if let Err(e) = dev.set_control(v4l::Control { id, value }) {
+            if result.is_ok() {
+                result = Err(format!("{}", e));
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+impl IntegerControl {
+    /// A writable control with the given range, set to its minimum, for tests.
+    pub fn fixture(id: u32, min: i64, max: i64, step: u64) -> Self {
+        Self {
+            id,
+            name: String::from("Test"),
+            min,
+            max,
+            step,
+            default: min,
+            value: min,
+            flags: v4l::control::Flags::empty(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snap_rounds_to_the_nearest_step() {
+        let control = IntegerControl::fixture(1, 0, 100, 10);
+        assert_eq!(control.snap(14), 10);
+        assert_eq!(control.snap(15), 20);
+        assert_eq!(control.snap(60), 60);
+    }
+
+    #[test]
+    fn snap_counts_steps_from_the_minimum() {
+        let control = IntegerControl::fixture(1, -5, 15, 4);
+        assert_eq!(control.snap(0), -1);
+        assert_eq!(control.snap(1), 3);
+    }
+
+    #[test]
+    fn snap_clamps_to_the_range() {
+        let control = IntegerControl::fixture(1, 0, 100, 10);
+        assert_eq!(control.snap(-5), 0);
+        assert_eq!(control.snap(250), 100);
+    }
+
+    #[test]
+    fn snap_handles_64_bit_ranges() {
+        let control = IntegerControl::fixture(1, i64::MIN, i64::MAX, 1);
+        assert_eq!(control.snap(i64::MAX), i64::MAX);
+        assert_eq!(control.snap(i64::MIN), i64::MIN);
+        assert_eq!(control.snap(42), 42);
+    }
+
+    #[test]
+    fn coarse_step_is_at_least_one_step() {
+        assert_eq!(IntegerControl::fixture(1, 0, 1000, 5).coarse_step(), 100);
+        assert_eq!(IntegerControl::fixture(1, 0, 10, 4).coarse_step(), 4);
+    }
+
+    #[test]
+    fn nudge_moves_by_fine_and_coarse_steps() {
+        let control = IntegerControl::fixture(1, 0, 1000, 5);
+        assert_eq!(control.nudge(500, 1, false), 505);
+        assert_eq!(control.nudge(500, -1, false), 495);
+        assert_eq!(control.nudge(500, 1, true), 600);
+        assert_eq!(control.nudge(500, -1, true), 400);
+    }
+
+    #[test]
+    fn nudge_stops_at_the_ends() {
+        let control = IntegerControl::fixture(1, 0, 1000, 5);
+        assert_eq!(control.nudge(950, 1, true), 1000);
+        assert_eq!(control.nudge(0, -1, false), 0);
+        let wide = IntegerControl::fixture(1, i64::MIN, i64::MAX, 1);
+        assert_eq!(wide.nudge(i64::MAX, 1, true), i64::MAX);
+        assert_eq!(wide.nudge(i64::MIN, -1, true), i64::MIN);
+    }
+}
@@ -0,0 +1,296 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::f64::consts::PI;
+use std::time::{Duration, Instant};
+
+use crate::cid;
+use crate::device::{find_control, DeviceControls};
+use crate::scopes::luma;
+use crate::stream::Frame;
+
+/// Exposures tried, in the 100 µs units of `exposure_time_absolute`: a whole
+/// period of 50 Hz mains flicker, a whole period of 60 Hz mains flicker, and
+/// a short one where any banding shows the most.
+const EXPOSURES: [i64; 3] = [100, 83, 20];
+/// Frames scored at each exposure.
+const FRAMES_PER_EXPOSURE: usize = 8;
+/// Time for a new exposure to show up in the frames.
+const SETTLE: Duration = Duration::from_millis(800);
+/// Columns sampled per row at most.
+const MAX_SAMPLES: u32 = 160;
+/// How far the band frequency may be from the expected one, since the time
+/// per row includes blanking we can't see.
+const BAND_SLACK: f64 = 0.15;
+/// How much stronger banding must be at the wrong exposure to call it.
+const MIN_CONTRAST: f64 = 1.5;
+const FALLBACK_FPS: f64 = 30.0;
+
+/// The mains frequency behind the lighting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mains {
+    Hz50,
+    Hz60,
+}
+
+impl Mains {
+    /// The `POWER_LINE_FREQUENCY` menu entry that cancels this flicker.
+    pub fn menu_value(&self) -> i64 {
+        match self {
+            Mains::Hz50 => cid::POWER_LINE_50HZ,
+            Mains::Hz60 => cid::POWER_LINE_60HZ,
+        }
+    }
+}
+
+impl AsRef<str> for Mains {
+    fn as_ref(&self) -> &str {
+        match self {
+            Mains::Hz50 => "50 Hz",
+            Mains::Hz60 => "60 Hz",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flicker {
+    Found(Mains),
+    /// The banding doesn't change with exposure, so the lighting doesn't flicker.
+    None,
+    /// There is banding, but it doesn't point to either frequency.
+    Unclear,
+}
+
+/// What to do after a frame while detecting. Every control involved is an
+/// integer or a menu, so values are plain integers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DetectStep {
+    Wait,
+    Write(Vec<(u32, i64)>),
+    /// Detection is over: put these values back and report.
+    Done(Flicker, Vec<(u32, i64)>),
+}
+
+/// Rolling shutter turns light flickering at twice the mains frequency into
+/// horizontal bands. Exposing for a whole flicker period evens them out, so
+/// the exposure that shows the least banding gives the frequency away.
+#[derive(Debug, Clone)]
+pub struct Detection {
+    exposure: u32,
+    exposures: Vec<i64>,
+    /// Values in effect before detecting.
+    restore: Vec<(u32, i64)>,
+    /// Banding of each scored frame, by exposure.
+    banding: Vec<Vec<f64>>,
+    /// Whether the current exposure has been written.
+    moved: bool,
+    until: Instant,
+    frame_times: Vec<Instant>,
+}
+
+impl Detection {
+    /// Returns the detection and the writes that prepare for it: anti-flicker
+    /// and auto exposure off. `None` without power line frequency and
+    /// absolute exposure controls. `current` gives the value in effect.
+    pub fn new(controls: &[DeviceControls], current: impl Fn(u32, i64) -> i64) -> Option<(Self, Vec<(u32, i64)>)> {
+        let Some(DeviceControls::Menu(power_line)) = find_control(controls, cid::POWER_LINE_FREQUENCY) else {
+            return None;
+        };
+        let Some(DeviceControls::Integer(exposure)) = find_control(controls, cid::EXPOSURE_ABSOLUTE) else {
+            return None;
+        };
+        let mut restore = vec![(power_line.id, current(power_line.id, power_line.value.unwrap_or(0) as i64))];
+        let mut prepare = vec![(power_line.id, cid::POWER_LINE_DISABLED)];
+        if let Some(DeviceControls::Menu(auto)) = find_control(controls, cid::EXPOSURE_AUTO) {
+            restore.push((auto.id, current(auto.id, auto.value.unwrap_or(0) as i64)));
+            prepare.push((auto.id, cid::EXPOSURE_MANUAL));
+        }
+        restore.push((exposure.id, current(exposure.id, exposure.value)));
+
+        let detection = Self {
+            exposure: exposure.id,
+            exposures: EXPOSURES.iter().map(|value| exposure.snap(*value)).collect(),
+            restore,
+            banding: vec![Vec::new(); EXPOSURES.len()],
+            moved: false,
+            until: Instant::now() + SETTLE,
+            frame_times: Vec::new(),
+        };
+        Some((detection, prepare))
+    }
+
+    /// The values in effect before detecting, to put back when it stops early.
+    pub fn into_restore(self) -> Vec<(u32, i64)> {
+        self.restore
+    }
+
+    /// The share of frames scored so far, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        let scored: usize = self.banding.iter().map(Vec::len).sum();
+        scored as f32 / (EXPOSURES.len() * FRAMES_PER_EXPOSURE) as f32
+    }
+
+    pub fn frame(&mut self, frame: &Frame) -> DetectStep {
+        let now = Instant::now();
+        self.frame_times.push(now);
+        if now < self.until {
+            return DetectStep::Wait;
+        }
+        let Some(current) = self.banding.iter().position(|scores| scores.len() < FRAMES_PER_EXPOSURE) else {
+            return DetectStep::Done(self.verdict(), self.restore.clone());
+        };
+        if !self.moved {
+            self.moved = true;
+            self.until = now + SETTLE;
+            return DetectStep::Write(vec![(self.exposure, self.exposures[current])]);
+        }
+        self.banding[current].push(banding(frame, self.fps()));
+        if self.banding[current].len() == FRAMES_PER_EXPOSURE {
+            self.moved = false;
+        }
+        DetectStep::Wait
+    }
+
+    /// Frame rate from the frames seen so far.
+    fn fps(&self) -> f64 {
+        match (self.frame_times.first(), self.frame_times.last()) {
+            (Some(first), Some(last)) if self.frame_times.len() > 4 && last > first => {
+                (self.frame_times.len() - 1) as f64 / last.duration_since(*first).as_secs_f64()
+            }
+            _ => FALLBACK_FPS,
+        }
+    }
+
+    fn verdict(&self) -> Flicker {
+        let median = |scores: &Vec<f64>| {
+            let mut scores = scores.clone();
+            scores.sort_by(f64::total_cmp);
+            scores.get(scores.len() / 2).copied().unwrap_or(0.0).max(f64::EPSILON)
+        };
+        let [hz50, hz60, short] = [0, 1, 2].map(|i| median(&self.banding[i]));
+        let least = hz50.min(hz60);
+        if short < least * MIN_CONTRAST && hz50.max(hz60) < least * MIN_CONTRAST {
+            return Flicker::None;
+        }
+        if self.exposures[0] == self.exposures[1] || hz50.max(hz60) < least * MIN_CONTRAST {
+            return Flicker::Unclear;
+        }
+        if hz50 < hz60 {
+            Flicker::Found(Mains::Hz50)
+        } else {
+            Flicker::Found(Mains::Hz60)
+        }
+    }
+}
+
+/// Strength of horizontal bands at the spacing 100 Hz and 120 Hz flicker
+/// would have: the peak of the row brightness spectrum in that range.
+fn banding(frame: &Frame, fps: f64) -> f64 {
+    let (width, height) = (frame.width as usize, frame.height as usize);
+    if width == 0 || height < 8 {
+        return 0.0;
+    }
+    let step = (frame.width / MAX_SAMPLES).max(1) as usize;
+    let rows: Vec<f64> = (0..height)
+        .map(|y| {
+            let (sum, count) = (width / 10..width * 9 / 10).step_by(step).fold((0.0, 0.0), |(sum, count), x| {
+                let i = (y * width + x) * 4;
+                match frame.pixels.get(i..i + 4) {
+                    Some(px) => (sum + luma(px) as f64, count + 1.0),
+                    None => (sum, count),
+                }
+            });
+            if count > 0.0 { sum / count } else { 0.0 }
+        })
+        .collect();
+    let mean = rows.iter().sum::<f64>() / height as f64;
+    if mean < 1.0 {
+        return 0.0;
+    }
+    // Relative to the mean, so exposures compare, and windowed against leakage.
+    let rows: Vec<f64> = rows.iter()
+        .enumerate()
+        .map(|(y, level)| {
+            let window = 0.5 - 0.5 * (2.0 * PI * y as f64 / (height - 1) as f64).cos();
+            (level / mean - 1.0) * window
+        })
+        .collect();
+
+    // Cycles over the frame height for each flicker frequency, taking the
+    // visible rows as the whole frame time.
+    let cycles = |flicker: f64| flicker / fps;
+    let (low, high) = (cycles(100.0) * (1.0 - BAND_SLACK), cycles(120.0) * (1.0 + BAND_SLACK));
+    let resolution = 0.25;
+    let mut peak: f64 = 0.0;
+    let mut k = low.max(1.0);
+    while k <= high.min(height as f64 / 2.0) {
+        let (re, im) = rows.iter().enumerate().fold((0.0, 0.0), |(re, im), (y, value)| {
+            let angle = 2.0 * PI * k * y as f64 / height as f64;
+            (re + value * angle.cos(), im - value * angle.sin())
+        });
+        peak = peak.max((re * re + im * im) / height as f64);
+        k += resolution;
+    }
+    peak
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detection(banding: [f64; 3]) -> Detection {
+        Detection {
+            exposure: cid::EXPOSURE_ABSOLUTE,
+            exposures: EXPOSURES.to_vec(),
+            restore: Vec::new(),
+            banding: banding.iter().map(|score| vec![*score; FRAMES_PER_EXPOSURE]).collect(),
+            moved: false,
+            until: Instant::now(),
+            frame_times: Vec::new(),
+        }
+    }
+
+    /// Horizontal bands completing `cycles` periods over the frame height.
+    fn banded(cycles: f64, depth: f64) -> Frame {
+        let (width, height) = (64, 120);
+        let pixels = (0..height)
+            .flat_map(|y| {
+                let v = (128.0 + depth * (2.0 * PI * cycles * y as f64 / height as f64).sin()) as u8;
+                [v, v, v, 255].repeat(width as usize)
+            })
+            .collect();
+        Frame { width, height, pixels }
+    }
+
+    #[test]
+    fn least_banding_at_a_whole_period_gives_the_frequency() {
+        assert_eq!(detection([0.1, 1.0, 2.0]).verdict(), Flicker::Found(Mains::Hz50));
+        assert_eq!(detection([1.0, 0.1, 2.0]).verdict(), Flicker::Found(Mains::Hz60));
+    }
+
+    #[test]
+    fn banding_that_ignores_exposure_is_no_flicker() {
+        assert_eq!(detection([1.0, 1.1, 1.2]).verdict(), Flicker::None);
+    }
+
+    #[test]
+    fn banding_that_fits_neither_period_is_unclear() {
+        assert_eq!(detection([1.0, 1.2, 5.0]).verdict(), Flicker::Unclear);
+    }
+
+    #[test]
+    fn exposures_that_snap_together_are_unclear() {
+        let mut detection = detection([0.1, 1.0, 2.0]);
+        detection.exposures = vec![100, 100, 20];
+        assert_eq!(detection.verdict(), Flicker::Unclear);
+    }
+
+    #[test]
+    fn banding_picks_up_bands_at_the_flicker_spacing() {
+        let fps = 30.0;
+        let flicker = banding(&banded(100.0 / fps, 40.0), fps);
+        let other = banding(&banded(20.0, 40.0), fps);
+        let flat = banding(&banded(0.0, 0.0), fps);
+        assert!(flicker > other * 1000.0, "{flicker} {other}");
+        assert_eq!(flat, 0.0);
+    }
+}
@@ -0,0 +1,250 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::time::{Duration, Instant};
+
+use crate::device::IntegerControl;
+use crate::scopes::luma;
+use crate::stream::Frame;
+
+/// Focus positions tried across the range.
+const SWEEP_STEPS: i64 = 24;
+/// Time for the lens to get to the next position and the frame to catch up.
+const STEP_SETTLE: Duration = Duration::from_millis(350);
+/// Time for autofocus to let go, and for the lens to get to the start of the range.
+const START_SETTLE: Duration = Duration::from_millis(1200);
+/// Pixels sampled along each axis at most.
+const MAX_SAMPLES: u32 = 480;
+const CURVE_WIDTH: u32 = 320;
+const CURVE_HEIGHT: u32 = 120;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FocusRegion {
+    /// The middle third of the frame.
+    #[default]
+    Center,
+    /// The middle tenth, for a small subject.
+    Spot,
+    Whole,
+}
+
+impl FocusRegion {
+    pub const ALL: [FocusRegion; 3] = [FocusRegion::Center, FocusRegion::Spot, FocusRegion::Whole];
+
+    /// Left, top, right and bottom of the region in a frame of the given size.
+    fn bounds(&self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let share = match self {
+            FocusRegion::Center => 3,
+            FocusRegion::Spot => 10,
+            FocusRegion::Whole => 1,
+        };
+        let (w, h) = (width / share, height / share);
+        let (left, top) = ((width - w) / 2, (height - h) / 2);
+        (left, top, left + w, top + h)
+    }
+}
+
+impl AsRef<str> for FocusRegion {
+    fn as_ref(&self) -> &str {
+        match self {
+            FocusRegion::Center => "Center",
+            FocusRegion::Spot => "Spot",
+            FocusRegion::Whole => "Whole frame",
+        }
+    }
+}
+
+/// Variance of the Laplacian of the luma within the region: the sharper the
+/// picture, the stronger and more frequent its edges.
+pub fn sharpness(frame: &Frame, region: FocusRegion) -> f64 {
+    let (left, top, right, bottom) = region.bounds(frame.width, frame.height);
+    let step = ((right - left) / MAX_SAMPLES).max(1);
+    let at = |x: u32, y: u32| {
+        let i = ((y * frame.width + x) * 4) as usize;
+        frame.pixels.get(i..i + 4).map_or(0.0, |px| luma(px) as f64)
+    };
+    let (mut sum, mut sum_squares, mut count) = (0.0, 0.0, 0.0);
+    for y in (top.max(step)..bottom.min(frame.height.saturating_sub(step))).step_by(step as usize) {
+        for x in (left.max(step)..right.min(frame.width.saturating_sub(step))).step_by(step as usize) {
+            let laplacian = at(x - step, y) + at(x + step, y) + at(x, y - step) + at(x, y + step) - 4.0 * at(x, y);
+            sum += laplacian;
+            sum_squares += laplacian * laplacian;
+            count += 1.0;
+        }
+    }
+    if count == 0.0 {
+        return 0.0;
+    }
+    let mean = sum / count;
+    sum_squares / count - mean * mean
+}
+
+/// What to do after a frame of a focus sweep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SweepStep {
+    Wait,
+    /// Move the lens to this position.
+    Move(i64),
+    /// The sweep is over; settle on this position.
+    Done(i64),
+}
+
+/// Steps focus across its range, scoring one frame at each position once the lens has settled.
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    positions: Vec<i64>,
+    next: usize,
+    /// Position and sharpness of each scored frame.
+    scores: Vec<(i64, f64)>,
+    /// Whether the lens is at `positions[next - 1]`, waiting for a frame to score.
+    measuring: bool,
+    until: Instant,
+}
+
+impl Sweep {
+    pub fn new(focus: &IntegerControl) -> Self {
+        let span = focus.max - focus.min;
+        let mut positions: Vec<i64> = (0..=SWEEP_STEPS)
+            .map(|i| focus.snap(focus.min + span * i / SWEEP_STEPS))
+            .collect();
+        positions.dedup();
+        Self {
+            positions,
+            next: 0,
+            scores: Vec::new(),
+            measuring: false,
+            until: Instant::now() + START_SETTLE,
+        }
+    }
+
+    pub fn scores(&self) -> &[(i64, f64)] {
+        &self.scores
+    }
+
+    /// The share of positions scored so far, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        self.scores.len() as f32 / self.positions.len().max(1) as f32
+    }
+
+    /// Scores the frame if the lens has settled and says where to go next.
+    pub fn frame(&mut self, frame: &Frame, region: FocusRegion) -> SweepStep {
+        let now = Instant::now();
+        if now < self.until {
+            return SweepStep::Wait;
+        }
+        if self.measuring {
+            self.scores.push((self.positions[self.next - 1], sharpness(frame, region)));
+            self.measuring = false;
+        }
+        let Some(position) = self.positions.get(self.next).copied() else {
+            return SweepStep::Done(self.best().unwrap_or_default());
+        };
+        // The first move can cross the whole range from wherever autofocus left the lens.
+        self.until = now + if self.next == 0 { START_SETTLE } else { STEP_SETTLE };
+        self.next += 1;
+        self.measuring = true;
+        SweepStep::Move(position)
+    }
+
+    fn best(&self) -> Option<i64> {
+        self.scores.iter()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(position, _)| *position)
+    }
+}
+
+/// Plots sharpness against focus position as RGBA, marking the chosen position.
+pub fn curve_image(scores: &[(i64, f64)], chosen: i64) -> (u32, u32, Vec<u8>) {
+    let (width, height) = (CURVE_WIDTH, CURVE_HEIGHT);
+    let mut pixels = [16u8, 16, 16, 255].repeat((width * height) as usize);
+    let mut set = |x: u32, y: u32, color: [u8; 4]| {
+        if x < width && y < height {
+            let i = ((y * width + x) * 4) as usize;
+            pixels[i..i + 4].copy_from_slice(&color);
+        }
+    };
+    let (Some(min), Some(max)) = (scores.iter().map(|s| s.0).min(), scores.iter().map(|s| s.0).max()) else {
+        return (width, height, pixels);
+    };
+    let top = scores.iter().map(|s| s.1).fold(0.0, f64::max).max(f64::EPSILON);
+    let span = (max - min).max(1) as f64;
+    let point = |(position, score): (i64, f64)| {
+        let x = ((position - min) as f64 / span * (width - 1) as f64).round() as u32;
+        let y = ((1.0 - score / top) * (height - 1) as f64).round() as u32;
+        (x, y)
+    };
+
+    let (marker, _) = point((chosen, 0.0));
+    for y in 0..height {
+        set(marker, y, [230, 180, 40, 255]);
+    }
+    // Straight segments between neighbouring positions.
+    let mut sorted = scores.to_vec();
+    sorted.sort_by_key(|s| s.0);
+    for pair in sorted.windows(2) {
+        let ((x0, y0), (x1, y1)) = (point(pair[0]), point(pair[1]));
+        let steps = x1.abs_diff(x0).max(y1.abs_diff(y0)).max(1);
+        for i in 0..=steps {
+            let t = i as f32 / steps as f32;
+            let x = x0 as f32 + (x1 as f32 - x0 as f32) * t;
+            let y = y0 as f32 + (y1 as f32 - y0 as f32) * t;
+            set(x.round() as u32, y.round() as u32, [220, 220, 220, 255]);
+        }
+    }
+    for score in sorted {
+        let (x, y) = point(score);
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            set((x + dx).saturating_sub(1), (y + dy).saturating_sub(1), [120, 200, 255, 255]);
+        }
+    }
+    (width, height, pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn focus(min: i64, max: i64) -> IntegerControl {
+        IntegerControl::fixture(crate::cid::FOCUS_ABSOLUTE, min, max, 1)
+    }
+
+    fn frame(level: impl Fn(u32, u32) -> u8) -> Frame {
+        let (width, height) = (64, 64);
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .flat_map(|(x, y)| {
+                let v = level(x, y);
+                [v, v, v, 255]
+            })
+            .collect();
+        Frame { width, height, pixels }
+    }
+
+    #[test]
+    fn sweep_covers_the_range_evenly() {
+        let sweep = Sweep::new(&focus(0, 240));
+        assert_eq!(sweep.positions, (0..=24).map(|i| i * 10).collect::<Vec<i64>>());
+    }
+
+    #[test]
+    fn sweep_skips_repeated_positions() {
+        let sweep = Sweep::new(&focus(0, 5));
+        assert_eq!(sweep.positions, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn best_is_the_sharpest_position() {
+        let mut sweep = Sweep::new(&focus(0, 240));
+        assert_eq!(sweep.best(), None);
+        sweep.scores = vec![(0, 1.0), (10, 4.0), (20, 9.0), (30, 2.5)];
+        assert_eq!(sweep.best(), Some(20));
+    }
+
+    #[test]
+    fn sharp_edges_score_higher_than_soft_ones() {
+        let sharp = frame(|x, _| if (x / 2) % 2 == 0 { 40 } else { 220 });
+        let soft = frame(|x, _| (130.0 + 90.0 * (x as f64 / 6.0).sin()) as u8);
+        let flat = frame(|_, _| 128);
+        assert!(sharpness(&sharp, FocusRegion::Whole) > sharpness(&soft, FocusRegion::Whole));
+        assert_eq!(sharpness(&flat, FocusRegion::Whole), 0.0);
+    }
+}
@@ -0,0 +1,312 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use cosmic::iced::Alignment;
+use cosmic::{cosmic_theme, theme, widget, Element};
+use v4l::capability::Flags;
+use v4l::framesize::FrameSizeEnum;
+use v4l::video::Capture;
+
+use crate::app::{App, Message};
+use crate::device::{blocking, get_v4l_device_by_path, DeviceControls, VideoDevice};
+use crate::fl;
+
+/// Capability flags with the label shown for them, in the order they are listed.
+const CAPABILITIES: &[(Flags, &str)] = &[
+    (Flags::VIDEO_CAPTURE, "Video capture"),
+    (Flags::VIDEO_CAPTURE_MPLANE, "Multi-planar video capture"),
+    (Flags::VIDEO_OUTPUT, "Video output"),
+    (Flags::VIDEO_OUTPUT_MPLANE, "Multi-planar video output"),
+    (Flags::VIDEO_OVERLAY, "Video overlay"),
+    (Flags::VIDEO_OUTPUT_OVERLAY, "Video output overlay"),
+    (Flags::VIDEO_M2M, "Memory-to-memory"),
+    (Flags::VIDEO_M2M_MPLANE, "Multi-planar memory-to-memory"),
+    (Flags::VBI_CAPTURE, "VBI capture"),
+    (Flags::VBI_OUTPUT, "VBI output"),
+    (Flags::SLICED_VBI_CAPTURE, "Sliced VBI capture"),
+    (Flags::SLICED_VBI_OUTPUT, "Sliced VBI output"),
+    (Flags::RDS_CAPTURE, "RDS capture"),
+    (Flags::RDS_OUTPUT, "RDS output"),
+    (Flags::HW_FREQ_SEEK, "Hardware frequency seek"),
+    (Flags::TUNER, "Tuner"),
+    (Flags::AUDIO, "Audio"),
+    (Flags::RADIO, "Radio"),
+    (Flags::MODULATOR, "Modulator"),
+    (Flags::SDR_CAPTURE, "SDR capture"),
+    (Flags::SDR_OUTPUT, "SDR output"),
+    (Flags::META_CAPTURE, "Metadata capture"),
+    (Flags::META_OUTPUT, "Metadata output"),
+    (Flags::EXT_PIX_FORMAT, "Extended pixel format"),
+    (Flags::READ_WRITE, "Read/write I/O"),
+    (Flags::ASYNC_IO, "Asynchronous I/O"),
+    (Flags::STREAMING, "Streaming I/O"),
+    (Flags::TOUCH, "Touch"),
+    (Flags::DEVICE_CAPS, "Per-device capabilities"),
+];
+
+/// Everything we know about a device, gathered for the info page and bug reports.
+#[derive(Debug, Clone)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub path: String,
+    pub driver: String,
+    pub card: String,
+    pub bus: String,
+    pub version: String,
+    pub capabilities: Vec<String>,
+    pub formats: Vec<FormatInfo>,
+    pub control_counts: Vec<(String, usize)>,
+    pub usb: Option<UsbInfo>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FormatInfo {
+    pub fourcc: String,
+    pub description: String,
+    pub sizes: Vec<String>,
+}
+
+/// Details of the USB device behind a video node, read from sysfs.
+#[derive(Debug, Clone, Default)]
+pub struct UsbInfo {
+    pub vendor_id: String,
+    pub product_id: String,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    pub serial: Option<String>,
+    pub speed: Option<String>,
+    pub bcd_device: Option<String>,
+}
+
+pub fn get_device_info(device: &VideoDevice) -> DeviceInfo {
+    let caps = &device.capabilities;
+    let formats = get_formats(&device.path).unwrap_or_else(|err| {
+        println!("Could not enumerate formats: {}", err);
+        Vec::new()
+    });
+
+    DeviceInfo {
+        name: device.name.clone(),
+        path: device.path.clone(),
+        driver: caps.driver.clone(),
+        card: caps.card.clone(),
+        bus: caps.bus.clone(),
+        version: format!("{}.{}.{}", caps.version.0, caps.version.1, caps.version.2),
+        capabilities: CAPABILITIES
+            .iter()
+            .filter(|(flag, _)| caps.capabilities.contains(*flag))
+            .map(|(_, label)| String::from(*label))
+            .collect(),
+        formats,
+        control_counts: count_controls(&device.controls),
+        usb: get_usb_info(&device.path),
+    }
+}
+
+pub async fn load_device_info(device: Arc<VideoDevice>) -> Result<DeviceInfo, String> {
+    blocking(move || Ok(get_device_info(&device))).await
+}
+
+fn get_formats(path: &str) -> Result<Vec<FormatInfo>, String> {
+    let dev = get_v4l_device_by_path(path)?;
+    let formats = dev.enum_formats().map_err(|e| format!("{}", e))?;
+
+    Ok(formats.into_iter().map(|format| {
+        let sizes = dev.enum_framesizes(format.fourcc)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|size| match size.size {
+                FrameSizeEnum::Discrete(size) => size.to_string(),
+                FrameSizeEnum::Stepwise(size) => size.to_string(),
+            })
+            .collect();
+        FormatInfo {
+            fourcc: format.fourcc.to_string(),
+            description: format.description,
+            sizes,
+        }
+    }).collect())
+}
+
+fn count_controls(controls: &[DeviceControls]) -> Vec<(String, usize)> {
+    let mut counts = Vec::new();
+    let ungrouped = controls.iter()
+        .filter(|control| !matches!(control, DeviceControls::ControlGroup(_)))
+        .count();
+    if ungrouped > 0 {
+        counts.push((fl!("ungrouped-controls"), ungrouped));
+    }
+    controls.iter().for_each(|control| {
+        if let DeviceControls::ControlGroup(group) = control {
+            counts.push((group.name.clone(), group.controls.len()));
+        }
+    });
+    counts
+}
+
+/// Walks up from the video node's sysfs device to the USB device that owns it.
+fn get_usb_device_dir(path: &str) -> Option<PathBuf> {
+    let node = Path::new(path).file_name()?;
+    let device_dir = Path::new("/sys/class/video4linux")
+        .join(node)
+        .join("device")
+        .canonicalize()
+        .ok()?;
+    device_dir.ancestors()
+        .find(|dir| dir.join("idVendor").exists())
+        .map(Path::to_path_buf)
+}
+
+fn get_usb_info(path: &str) -> Option<UsbInfo> {
+    let dir = get_usb_device_dir(path)?;
+    let read = |name: &str| {
+        fs::read_to_string(dir.join(name))
+            .ok()
+            .map(|val| val.trim().to_string())
+            .filter(|val| !val.is_empty())
+    };
+
+    Some(UsbInfo {
+        vendor_id: read("idVendor")?,
+        product_id: read("idProduct")?,
+        manufacturer: read("manufacturer"),
+        product: read("product"),
+        serial: read("serial"),
+        speed: read("speed").map(|speed| format!("{} Mb/s", speed)),
+        bcd_device: read("bcdDevice"),
+    })
+}
+
+/// Formats a `bcdDevice` value like `0019` as `0.19`.
+fn format_bcd(bcd: &str) -> String {
+    match (bcd.get(..2), bcd.get(2..)) {
+        (Some(major), Some(minor)) if bcd.len() == 4 => {
+            let major = major.trim_start_matches('0');
+            format!("{}.{}", if major.is_empty() { "0" } else { major }, minor)
+        }
+        _ => bcd.to_string(),
+    }
+}
+
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Name         : {}", self.name)?;
+        writeln!(f, "Device node  : {}", self.path)?;
+        writeln!(f, "Driver       : {}", self.driver)?;
+        writeln!(f, "Card         : {}", self.card)?;
+        writeln!(f, "Bus          : {}", self.bus)?;
+        writeln!(f, "Version      : {}", self.version)?;
+        writeln!(f, "Capabilities : {}", self.capabilities.join(", "))?;
+        if let Some(usb) = &self.usb {
+            writeln!(f, "USB id       : {}:{}", usb.vendor_id, usb.product_id)?;
+            if let Some(manufacturer) = &usb.manufacturer {
+                writeln!(f, "Manufacturer : {}", manufacturer)?;
+            }
+            if let Some(product) = &usb.product {
+                writeln!(f, "Product      : {}", product)?;
+            }
+            if let Some(serial) = &usb.serial {
+                writeln!(f, "Serial       : {}", serial)?;
+            }
+            if let Some(speed) = &usb.speed {
+                writeln!(f, "USB speed    : {}", speed)?;
+            }
+            if let Some(bcd) = &usb.bcd_device {
+                writeln!(f, "Firmware     : {} (bcdDevice {})", format_bcd(bcd), bcd)?;
+            }
+        }
+        writeln!(f, "Formats:")?;
+        for format in &self.formats {
+            writeln!(f, "  {} ({}): {}", format.fourcc, format.description, format.sizes.join(", "))?;
+        }
+        writeln!(f, "Controls:")?;
+        for (class, count) in &self.control_counts {
+            writeln!(f, "  {}: {}", class, count)?;
+        }
+        Ok(())
+    }
+}
+
+impl App {
+
+pub fn device_info(&self) -> Element<Message> {
+    let cosmic_theme::Spacing { space_xxs, space_s, .. } = theme::active().cosmic().spacing;
+
+    let Some(info) = &self.device_info else {
+        return widget::text::text(fl!("device-load-failed")).into();
+    };
+
+    let item = |label: String, value: &str| {
+        widget::settings::item(label, widget::text::text(value.to_string()))
+    };
+
+    let device = widget::settings::section()
+        .title(fl!("device"))
+        .add(item(fl!("info-card"), &info.card))
+        .add(item(fl!("info-driver"), &info.driver))
+        .add(item(fl!("info-version"), &info.version))
+        .add(item(fl!("info-bus"), &info.bus))
+        .add(item(fl!("info-node"), &info.path))
+        .add(item(fl!("info-capabilities"), &info.capabilities.join("\n")));
+
+    let usb = info.usb.as_ref().map(|usb| {
+        let optional = |val: &Option<String>| val.clone().unwrap_or_else(|| String::from("—"));
+        widget::settings::section()
+            .title(fl!("info-usb"))
+            .add(item(fl!("info-usb-id"), &format!("{}:{}", usb.vendor_id, usb.product_id)))
+            .add(item(fl!("info-manufacturer"), &optional(&usb.manufacturer)))
+            .add(item(fl!("info-product"), &optional(&usb.product)))
+            .add(item(fl!("info-serial"), &optional(&usb.serial)))
+            .add(item(fl!("info-usb-speed"), &optional(&usb.speed)))
+            .add(item(
+                fl!("info-firmware"),
+                &usb.bcd_device.as_deref().map(format_bcd).unwrap_or_else(|| String::from("—")),
+            ))
+    });
+
+    let formats = info.formats.iter().fold(
+        widget::settings::section().title(fl!("info-formats")),
+        |section, format| {
+            section.add(item(
+                format!("{} ({})", format.fourcc, format.description),
+                &format.sizes.join("\n"),
+            ))
+        },
+    );
+
+    let controls = info.control_counts.iter().fold(
+        widget::settings::section().title(fl!("info-controls")),
+        |section, (class, count)| section.add(item(class.clone(), &count.to_string())),
+    );
+
+    let column = widget::column()
+        .spacing(space_s)
+        .push(
+            widget::row()
+                .align_y(Alignment::Center)
+                .spacing(space_xxs)
+                .push(widget::text::title3(info.name.clone()))
+                .push(widget::horizontal_space())
+                .push(
+                    widget::button::standard(fl!("copy-device-info"))
+                        .on_press(Message::CopyDeviceInfo)
+                )
+        )
+        .push(device);
+
+    let column = match usb {
+        Some(usb) => column.push(usb),
+        None => column,
+    };
+
+    column
+        .push(formats)
+        .push(controls)
+        .into()
+}
+
+}
@@ -0,0 +1,172 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::collections::HashMap;
+
+use cosmic::iced::keyboard::key::Named;
+use cosmic::iced::keyboard::Key;
+use cosmic::widget::menu::key_bind::{KeyBind, Modifier};
+
+use crate::app::MenuAction;
+
+/// Named keys that can be used in a key bind, with the label used to store them.
+const NAMED_KEYS: &[(&str, Named)] = &[
+    ("Left", Named::ArrowLeft),
+    ("Right", Named::ArrowRight),
+    ("Up", Named::ArrowUp),
+    ("Down", Named::ArrowDown),
+    ("PageUp", Named::PageUp),
+    ("PageDown", Named::PageDown),
+    ("Home", Named::Home),
+    ("End", Named::End),
+    ("Space", Named::Space),
+    ("Enter", Named::Enter),
+    ("Tab", Named::Tab),
+    ("Escape", Named::Escape),
+    ("Delete", Named::Delete),
+    ("Backspace", Named::Backspace),
+    ("F1", Named::F1),
+    ("F2", Named::F2),
+    ("F3", Named::F3),
+    ("F4", Named::F4),
+    ("F5", Named::F5),
+    ("F6", Named::F6),
+    ("F7", Named::F7),
+    ("F8", Named::F8),
+    ("F9", Named::F9),
+    ("F10", Named::F10),
+    ("F11", Named::F11),
+    ("F12", Named::F12),
+];
+
+pub fn default_key_binds() -> HashMap<MenuAction, String> {
+    let mut key_binds = HashMap::new();
+    key_binds.insert(MenuAction::NextDevice, String::from("Ctrl+PageDown"));
+    key_binds.insert(MenuAction::PreviousDevice, String::from("Ctrl+PageUp"));
+    key_binds.insert(MenuAction::SaveProfile, String::from("Ctrl+S"));
+    key_binds.insert(MenuAction::SwitchProfile, String::from("Ctrl+P"));
+    key_binds.insert(MenuAction::ResetControl, String::from("Ctrl+R"));
+    key_binds.insert(MenuAction::ResetGroup, String::from("Ctrl+Shift+R"));
+    key_binds.insert(MenuAction::ResetDevice, String::from("Ctrl+Alt+R"));
+    key_binds.insert(MenuAction::TogglePreview, String::from("Ctrl+Shift+P"));
+    key_binds.insert(MenuAction::ToggleSettings, String::from("Ctrl+,"));
+    key_binds
+}
+
+/// Builds the key binds used by the menu from the configured shortcuts.
+/// Shortcuts that cannot be parsed are skipped, and a shortcut used twice in
+/// a hand-edited config goes to the action listed first in the menu.
+pub fn key_binds(config: &HashMap<MenuAction, String>) -> HashMap<KeyBind, MenuAction> {
+    let mut key_binds = HashMap::new();
+    for action in MenuAction::ALL {
+        if let Some(bind) = config.get(action).and_then(|shortcut| parse_key_bind(shortcut)) {
+            key_binds.entry(bind).or_insert(*action);
+        }
+    }
+    key_binds
+}
+
+/// The other action already bound to the same keys as `shortcut`, if any.
+pub fn conflict(config: &HashMap<MenuAction, String>, action: MenuAction, shortcut: &str) -> Option<MenuAction> {
+    let bind = parse_key_bind(shortcut)?;
+    config
+        .iter()
+        .filter(|(other, _)| **other != action)
+        .find(|(_, other)| parse_key_bind(other).as_ref() == Some(&bind))
+        .map(|(other, _)| *other)
+}
+
+/// Parses a shortcut like `Ctrl+Shift+R` or `Alt+PageDown`. Modifiers may be
+/// given in any order and come out in a fixed one, so equal shortcuts compare equal.
+pub fn parse_key_bind(shortcut: &str) -> Option<KeyBind> {
+    let mut held = Vec::new();
+    let mut key = None;
+
+    for part in shortcut.split('+').map(str::trim) {
+        if key.is_some() {
+            return None;
+        }
+        match part.to_lowercase().as_str() {
+            "ctrl" | "control" => held.push(Modifier::Ctrl),
+            "alt" => held.push(Modifier::Alt),
+            "shift" => held.push(Modifier::Shift),
+            "super" => held.push(Modifier::Super),
+            _ => {
+                key = NAMED_KEYS
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(part))
+                    .map(|(_, named)| Key::Named(*named))
+                    .or_else(|| {
+                        let mut chars = part.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(c), None) => Some(Key::Character(c.to_lowercase().to_string().into())),
+                            _ => None,
+                        }
+                    });
+                key.as_ref()?;
+            }
+        }
+    }
+
+    let modifiers = [Modifier::Ctrl, Modifier::Alt, Modifier::Shift, Modifier::Super]
+        .into_iter()
+        .filter(|modifier| held.contains(modifier))
+        .collect();
+    key.map(|key| KeyBind { modifiers, key })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(shortcuts: &[(MenuAction, &str)]) -> HashMap<MenuAction, String> {
+        shortcuts.iter().map(|(action, shortcut)| (*action, shortcut.to_string())).collect()
+    }
+
+    #[test]
+    fn parses_modifiers_and_characters() {
+        let bind = parse_key_bind("Ctrl+Shift+R").unwrap();
+        assert!(bind.modifiers == vec![Modifier::Ctrl, Modifier::Shift]);
+        assert_eq!(bind.key, Key::Character("r".into()));
+    }
+
+    #[test]
+    fn parses_named_keys_in_any_case() {
+        let bind = parse_key_bind("alt + pagedown").unwrap();
+        assert!(bind.modifiers == vec![Modifier::Alt]);
+        assert_eq!(bind.key, Key::Named(Named::PageDown));
+    }
+
+    #[test]
+    fn modifier_order_and_repeats_do_not_matter() {
+        let bind = parse_key_bind("Shift+Ctrl+Shift+S").unwrap();
+        assert!(bind == parse_key_bind("Ctrl+Shift+S").unwrap());
+        let shortcuts = config(&[(MenuAction::SaveProfile, "Ctrl+Shift+S")]);
+        assert_eq!(conflict(&shortcuts, MenuAction::TogglePreview, "Shift+Ctrl+S"), Some(MenuAction::SaveProfile));
+    }
+
+    #[test]
+    fn rejects_malformed_shortcuts() {
+        for shortcut in ["", "Ctrl+", "Ctrl+Shift", "Ctrl+RR", "R+Ctrl", "Ctrl+Nope"] {
+            assert!(parse_key_bind(shortcut).is_none(), "{shortcut}");
+        }
+    }
+
+    #[test]
+    fn finds_conflicts_with_other_actions_only() {
+        let config = config(&[(MenuAction::SaveProfile, "Ctrl+S")]);
+        assert_eq!(conflict(&config, MenuAction::ResetControl, "ctrl+s"), Some(MenuAction::SaveProfile));
+        assert_eq!(conflict(&config, MenuAction::SaveProfile, "Ctrl+S"), None);
+        assert_eq!(conflict(&config, MenuAction::ResetControl, "Ctrl+Shift+S"), None);
+    }
+
+    #[test]
+    fn a_shared_shortcut_goes_to_the_first_action() {
+        let config = config(&[
+            (MenuAction::SaveProfile, "Ctrl+S"),
+            (MenuAction::NextDevice, "Ctrl+S"),
+        ]);
+        let binds = key_binds(&config);
+        assert_eq!(binds.len(), 1);
+        assert_eq!(binds.values().next(), Some(&MenuAction::NextDevice));
+    }
+}
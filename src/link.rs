@@ -0,0 +1,149 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::sync::Arc;
+
+use v4l::control::Value as ControlValue;
+
+use crate::device::{
+    blocking, find_control, find_control_by_name, get_device_by_path, get_v4l_device_by_path,
+    is_gate_master, ControlData, DeviceControls, VideoDevice,
+};
+
+/// How a change made on one camera landed on a linked one.
+#[derive(Debug, Clone)]
+pub enum LinkResult {
+    Applied,
+    /// The device took a different value than asked for, after snapping to its
+    /// range and step or clamping in the driver.
+    Clamped { requested: i64, written: i64 },
+    Missing,
+    Failed(String),
+}
+
+impl LinkResult {
+    /// A short description for the deviation indicator, `None` when in sync.
+    pub fn deviation(&self) -> Option<String> {
+        match self {
+            LinkResult::Applied => None,
+            LinkResult::Clamped { requested, written } => {
+                Some(format!("{} → {}", requested, written))
+            }
+            LinkResult::Missing => Some(String::from("no such control")),
+            LinkResult::Failed(err) => Some(err.clone()),
+        }
+    }
+}
+
+/// A linked camera other than the selected one, as shown on the device page.
+#[derive(Debug, Clone)]
+pub struct LinkStatus {
+    pub name: String,
+    /// Control name and what went wrong, for each control that is out of sync.
+    pub deviations: Vec<(String, String)>,
+}
+
+/// Copies the values a linked write can carry; `ControlValue` isn't `Clone`.
+pub fn copy_value(value: &ControlValue) -> Option<ControlValue> {
+    match value {
+        ControlValue::None => Some(ControlValue::None),
+        ControlValue::Integer(val) => Some(ControlValue::Integer(*val)),
+        ControlValue::Boolean(val) => Some(ControlValue::Boolean(*val)),
+        _ => None,
+    }
+}
+
+/// What a linked write did, with the camera's controls to reuse next time.
+#[derive(Debug, Clone)]
+pub struct LinkWrite {
+    /// `None` when the controls need reading again before the next write.
+    pub device: Option<Arc<VideoDevice>>,
+    /// Control name and outcome, for each value written.
+    pub results: Vec<(String, LinkResult)>,
+}
+
+/// Applies changes made on another camera to the device at `path` in order,
+/// matching each control by id first and by name otherwise. `device` holds the
+/// camera's controls from an earlier write; they are read again when missing
+/// and after an auto mode is written, since that changes which are active.
+pub async fn write_linked(
+    path: String,
+    device: Option<Arc<VideoDevice>>,
+    values: Vec<(String, u32, ControlValue)>,
+) -> LinkWrite {
+    let names: Vec<String> = values.iter().map(|(name, _, _)| name.clone()).collect();
+    let result = blocking(move || Ok(apply_all(&path, device, values))).await;
+    result.unwrap_or_else(|err| LinkWrite {
+        device: None,
+        results: names.into_iter().map(|name| (name, LinkResult::Failed(err.clone()))).collect(),
+    })
+}
+
+fn apply_all(path: &str, mut device: Option<Arc<VideoDevice>>, values: Vec<(String, u32, ControlValue)>) -> LinkWrite {
+    let mut results = Vec::new();
+    for (name, id, value) in values {
+        let current = match device.take() {
+            Some(current) => current,
+            None => match get_device_by_path(path) {
+                Ok(loaded) => Arc::new(loaded),
+                Err(err) => {
+                    results.push((name, LinkResult::Failed(err)));
+                    continue;
+                }
+            },
+        };
+        let result = apply(&current, &name, id, value);
+        results.push((name, result));
+        // An auto mode changes which controls are active, so read them again.
+        if !is_gate_master(id) {
+            device = Some(current);
+        }
+    }
+    LinkWrite { device, results }
+}
+
+fn apply(device: &VideoDevice, name: &str, id: u32, value: ControlValue) -> LinkResult {
+    let control = find_control(&device.controls, id)
+        .filter(|control| control.name() == name)
+        .or_else(|| find_control_by_name(&device.controls, name));
+    let Some(control) = control else {
+        return LinkResult::Missing;
+    };
+
+    let (value, requested) = match (control, value) {
+        (DeviceControls::Integer(control), ControlValue::Integer(val)) => {
+            (ControlValue::Integer(control.snap(val)), Some(val))
+        }
+        (DeviceControls::Menu(control), ControlValue::Integer(val)) => {
+            if !control.menu_items.iter().any(|item| item.id as i64 == val) {
+                return LinkResult::Failed(format!("no menu entry {}", val));
+            }
+            (ControlValue::Integer(val), Some(val))
+        }
+        (DeviceControls::Boolean(_), ControlValue::Boolean(val)) => (ControlValue::Boolean(val), None),
+        (DeviceControls::Button(_), ControlValue::None) => (ControlValue::None, None),
+        _ => return LinkResult::Failed(String::from("different control type")),
+    };
+    if control.is_disabled() {
+        return LinkResult::Failed(String::from("control is inactive"));
+    }
+
+    let id = control.id();
+    let dev = match get_v4l_device_by_path(&device.path) {
+        Ok(dev) => dev,
+        Err(err) => return LinkResult::Failed(err),
+    };
+    if let Err(err) = dev.set_control(v4l::Control { id, value }) {
+        return LinkResult::Failed(format!("{}", err));
+    }
+
+    // Read back, since drivers may clamp on their own.
+    let Some(requested) = requested else {
+        return LinkResult::Applied;
+    };
+    match dev.control(id).map(|control| control.value) {
+        Ok(ControlValue::Integer(written)) if written != requested => {
+            LinkResult::Clamped { requested, written }
+        }
+        _ => LinkResult::Applied,
+    }
+}
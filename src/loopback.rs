@@ -0,0 +1,277 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+
+use v4l::capability::Flags;
+use v4l::context;
+use v4l::prelude::*;
+use v4l::video::Output as _;
+use v4l::{Format, FourCC};
+
+use crate::device::blocking;
+use crate::stream::Frame;
+
+const LOOPBACK_DRIVER: &str = "v4l2 loopback";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Rotation {
+    #[default]
+    None,
+    Quarter,
+    Half,
+    ThreeQuarters,
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 4] = [
+        Rotation::None,
+        Rotation::Quarter,
+        Rotation::Half,
+        Rotation::ThreeQuarters,
+    ];
+
+    fn degrees(&self) -> u32 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Quarter => 90,
+            Rotation::Half => 180,
+            Rotation::ThreeQuarters => 270,
+        }
+    }
+
+    fn from_degrees(degrees: u32) -> Option<Self> {
+        Rotation::ALL.into_iter().find(|rotation| rotation.degrees() == degrees)
+    }
+}
+
+impl AsRef<str> for Rotation {
+    fn as_ref(&self) -> &str {
+        match self {
+            Rotation::None => "0°",
+            Rotation::Quarter => "90°",
+            Rotation::Half => "180°",
+            Rotation::ThreeQuarters => "270°",
+        }
+    }
+}
+
+/// Software changes applied to frames on their way to the virtual camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Transform {
+    /// Digital zoom in percent, 100 shows the whole frame.
+    pub zoom: u32,
+    /// Where the zoomed area sits, from -100 (left, top) to 100 (right, bottom).
+    pub pan_x: i32,
+    pub pan_y: i32,
+    pub mirror: bool,
+    /// Clockwise; the picture is cropped to fill the output.
+    pub rotation: Rotation,
+    /// Added to each channel, from -100 to 100.
+    pub brightness: i32,
+    /// In percent, 100 leaves the picture as is.
+    pub contrast: i32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            zoom: 100,
+            pan_x: 0,
+            pan_y: 0,
+            mirror: false,
+            rotation: Rotation::default(),
+            brightness: 0,
+            contrast: 100,
+        }
+    }
+}
+
+impl Transform {
+    /// Parses the form written by `to_config`, e.g. `zoom=150,pan_x=-20,mirror=1`.
+    pub fn from_config(value: &str) -> Self {
+        let mut transform = Transform::default();
+        for (key, value) in value.split(',').filter_map(|item| item.trim().split_once('=')) {
+            match key {
+                "zoom" => transform.zoom = value.parse().unwrap_or(transform.zoom).clamp(100, 400),
+                "pan_x" => transform.pan_x = value.parse().unwrap_or(0).clamp(-100, 100),
+                "pan_y" => transform.pan_y = value.parse().unwrap_or(0).clamp(-100, 100),
+                "mirror" => transform.mirror = value == "1",
+                "rotate" => {
+                    transform.rotation = value.parse().ok()
+                        .and_then(Rotation::from_degrees)
+                        .unwrap_or_default();
+                }
+                "brightness" => transform.brightness = value.parse().unwrap_or(0).clamp(-100, 100),
+                "contrast" => transform.contrast = value.parse().unwrap_or(100).clamp(50, 200),
+                _ => (),
+            }
+        }
+        transform
+    }
+
+    pub fn to_config(&self) -> String {
+        format!(
+            "zoom={},pan_x={},pan_y={},mirror={},rotate={},brightness={},contrast={}",
+            self.zoom,
+            self.pan_x,
+            self.pan_y,
+            self.mirror as u8,
+            self.rotation.degrees(),
+            self.brightness,
+            self.contrast,
+        )
+    }
+
+    /// Returns the transformed frame, the same size as the input.
+    pub fn apply(&self, frame: &Frame) -> Frame {
+        let (width, height) = (frame.width, frame.height);
+        let lut = self.lut();
+        let mut pixels = vec![0; frame.pixels.len()];
+        if width == 0 || height == 0 {
+            return Frame { width, height, pixels };
+        }
+
+        // Work in the rotated source, where the output is a scaled window.
+        let rotated = matches!(self.rotation, Rotation::Quarter | Rotation::ThreeQuarters);
+        let (source_width, source_height) = if rotated { (height, width) } else { (width, height) };
+        let (source_width, source_height) = (source_width as f32, source_height as f32);
+        let fill = (width as f32 / source_width).max(height as f32 / source_height);
+        let scale = fill * self.zoom as f32 / 100.0;
+        let (visible_width, visible_height) = (width as f32 / scale, height as f32 / scale);
+        let center_x = source_width / 2.0 + (source_width - visible_width) / 2.0 * self.pan_x as f32 / 100.0;
+        let center_y = source_height / 2.0 + (source_height - visible_height) / 2.0 * self.pan_y as f32 / 100.0;
+
+        for y in 0..height {
+            for x in 0..width {
+                let out_x = if self.mirror { width - 1 - x } else { x };
+                let u = (center_x + (out_x as f32 + 0.5 - width as f32 / 2.0) / scale) as u32;
+                let v = (center_y + (y as f32 + 0.5 - height as f32 / 2.0) / scale) as u32;
+                let (u, v) = (u.min(source_width as u32 - 1), v.min(source_height as u32 - 1));
+                // Undo the rotation to find the pixel in the captured frame.
+                let (sx, sy) = match self.rotation {
+                    Rotation::None => (u, v),
+                    Rotation::Quarter => (v, height - 1 - u),
+                    Rotation::Half => (width - 1 - u, height - 1 - v),
+                    Rotation::ThreeQuarters => (width - 1 - v, u),
+                };
+                let from = ((sy * width + sx) * 4) as usize;
+                let to = ((y * width + x) * 4) as usize;
+                if let Some(px) = frame.pixels.get(from..from + 4) {
+                    pixels[to..to + 4].copy_from_slice(&[lut[px[0] as usize], lut[px[1] as usize], lut[px[2] as usize], px[3]]);
+                }
+            }
+        }
+        Frame { width, height, pixels }
+    }
+
+    /// Brightness and contrast as a lookup table, with contrast pivoting around mid grey.
+    fn lut(&self) -> [u8; 256] {
+        let mut lut = [0; 256];
+        for (i, value) in lut.iter_mut().enumerate() {
+            let level = (i as i32 - 128) * self.contrast / 100 + 128 + self.brightness * 255 / 100;
+            *value = level.clamp(0, 255) as u8;
+        }
+        lut
+    }
+}
+
+/// Lists v4l2loopback nodes as path and name pairs.
+pub fn get_outputs() -> Vec<(String, String)> {
+    context::enum_devices()
+        .iter()
+        .filter_map(|node| {
+            let path = node.path().to_str()?.to_string();
+            let caps = Device::with_path(&path).ok()?.query_caps().ok()?;
+            let output = caps.driver == LOOPBACK_DRIVER && caps.capabilities.contains(Flags::VIDEO_OUTPUT);
+            output.then(|| (path, node.name().unwrap_or(caps.card)))
+        })
+        .collect()
+}
+
+pub async fn load_outputs() -> Result<Vec<(String, String)>, String> {
+    blocking(|| Ok(get_outputs())).await
+}
+
+/// A v4l2loopback node being fed YUYV frames.
+pub struct Output {
+    /// Kept open so the format stays set.
+    _device: Device,
+    file: File,
+    width: u32,
+    height: u32,
+}
+
+impl Output {
+    pub fn open(path: &str, width: u32, height: u32) -> Result<Self, String> {
+        let device = Device::with_path(path).map_err(|e| format!("{}", e))?;
+        device.set_format(&Format::new(width, height, FourCC::new(b"YUYV")))
+            .map_err(|e| format!("{}", e))?;
+        let file = OpenOptions::new().write(true).open(path).map_err(|e| format!("{}", e))?;
+        Ok(Self { _device: device, file, width, height })
+    }
+
+    pub fn write(&mut self, frame: &Frame) -> Result<(), String> {
+        if (frame.width, frame.height) != (self.width, self.height) {
+            return Err(String::from("Frame size changed"));
+        }
+        self.file.write_all(&encode_yuyv(frame)).map_err(|e| format!("{}", e))
+    }
+}
+
+/// BT.601 limited range, with the chroma of each pixel pair averaged.
+fn encode_yuyv(frame: &Frame) -> Vec<u8> {
+    let mut data = Vec::with_capacity((frame.width * frame.height * 2) as usize);
+    for row in frame.pixels.chunks_exact((frame.width * 4) as usize) {
+        for pair in row.chunks(8) {
+            let first = [pair[0] as i32, pair[1] as i32, pair[2] as i32];
+            let second = match pair.get(4..7) {
+                Some(px) => [px[0] as i32, px[1] as i32, px[2] as i32],
+                None => first,
+            };
+            let luma = |[r, g, b]: [i32; 3]| (((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8;
+            let [r, g, b] = [0, 1, 2].map(|c| (first[c] + second[c]) / 2);
+            let u = (((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8;
+            let v = (((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8;
+            data.extend_from_slice(&[luma(first), u, luma(second), v]);
+        }
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_round_trips() {
+        let transform = Transform {
+            zoom: 250,
+            pan_x: -40,
+            pan_y: 75,
+            mirror: true,
+            rotation: Rotation::ThreeQuarters,
+            brightness: -12,
+            contrast: 130,
+        };
+        assert_eq!(Transform::from_config(&transform.to_config()), transform);
+        assert_eq!(Transform::from_config(&Transform::default().to_config()), Transform::default());
+    }
+
+    #[test]
+    fn config_values_are_clamped() {
+        let transform = Transform::from_config("zoom=50,pan_x=300,pan_y=-300,brightness=500,contrast=10");
+        assert_eq!(transform.zoom, 100);
+        assert_eq!(transform.pan_x, 100);
+        assert_eq!(transform.pan_y, -100);
+        assert_eq!(transform.brightness, 100);
+        assert_eq!(transform.contrast, 50);
+    }
+
+    #[test]
+    fn bad_config_falls_back_to_defaults() {
+        assert_eq!(Transform::from_config(""), Transform::default());
+        assert_eq!(Transform::from_config("zoom=wide, rotate=45, mirror=yes, junk"), Transform::default());
+        assert_eq!(Transform::from_config(" rotate=90 ").rotation, Rotation::Quarter);
+    }
+}
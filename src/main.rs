@@ -3,10 +3,28 @@
 use app::App;
 /// The `app` module is used by convention to indicate the main component of our application.
 mod app;
+mod auto;
+mod cid;
+mod cli;
+mod compare;
 mod content;
 mod core;
 mod device;
+mod flicker;
+mod focus;
+mod info;
+mod key_binds;
+mod link;
+mod loopback;
+mod overlays;
+mod ptz;
+mod record;
+mod scopes;
+mod snapshot;
+mod stats;
 mod storage;
+mod stream;
+mod white_balance;
 mod widgets;
 mod settings;
 
@@ -16,6 +34,11 @@ mod settings;
 /// - `()` is the flags that your app needs to use before it starts.
 ///  If your app does not need any flags, you can pass in `()`.
 fn main() -> cosmic::iced::Result {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+
     let settings = cosmic::app::Settings::default()
         .exit_on_close(false);
     cosmic::app::run::<App>(settings, ())
@@ -0,0 +1,335 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::scopes::luma;
+use crate::stream::Frame;
+
+/// Width of a zebra stripe, in pixels.
+const STRIPE_WIDTH: u32 = 4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PeakingColor {
+    #[default]
+    Red,
+    Green,
+    Blue,
+    Yellow,
+    White,
+}
+
+impl PeakingColor {
+    pub const ALL: [PeakingColor; 5] = [
+        PeakingColor::Red,
+        PeakingColor::Green,
+        PeakingColor::Blue,
+        PeakingColor::Yellow,
+        PeakingColor::White,
+    ];
+
+    fn rgba(&self) -> [u8; 4] {
+        match self {
+            PeakingColor::Red => [255, 32, 32, 255],
+            PeakingColor::Green => [32, 255, 32, 255],
+            PeakingColor::Blue => [48, 96, 255, 255],
+            PeakingColor::Yellow => [255, 230, 0, 255],
+            PeakingColor::White => [255, 255, 255, 255],
+        }
+    }
+}
+
+impl AsRef<str> for PeakingColor {
+    fn as_ref(&self) -> &str {
+        match self {
+            PeakingColor::Red => "Red",
+            PeakingColor::Green => "Green",
+            PeakingColor::Blue => "Blue",
+            PeakingColor::Yellow => "Yellow",
+            PeakingColor::White => "White",
+        }
+    }
+}
+
+/// Exposure and focus aids drawn over the preview.
+#[derive(Debug, Clone, Copy)]
+pub struct Overlays {
+    pub zebra: bool,
+    /// Luma level, in percent, from which highlights get striped.
+    pub zebra_level: u8,
+    pub peaking: bool,
+    /// How strong an edge must be to be highlighted; lower marks more edges.
+    pub peaking_level: u8,
+    pub peaking_color: PeakingColor,
+}
+
+impl Default for Overlays {
+    fn default() -> Self {
+        Self {
+            zebra: false,
+            zebra_level: 95,
+            peaking: false,
+            peaking_level: 40,
+            peaking_color: PeakingColor::default(),
+        }
+    }
+}
+
+impl Overlays {
+    pub fn is_active(&self) -> bool {
+        self.zebra || self.peaking
+    }
+
+    /// Returns the frame's pixels with the enabled overlays drawn in.
+    pub fn apply(&self, frame: &Frame) -> Vec<u8> {
+        let mut pixels = frame.pixels.clone();
+        if self.peaking {
+            self.draw_peaking(frame, &mut pixels);
+        }
+        if self.zebra {
+            self.draw_zebra(frame, &mut pixels);
+        }
+        pixels
+    }
+
+    fn draw_zebra(&self, frame: &Frame, pixels: &mut [u8]) {
+        let threshold = (self.zebra_level as u32 * 255 / 100) as u8;
+        for (i, px) in frame.pixels.chunks_exact(4).enumerate() {
+            let (x, y) = (i as u32 % frame.width, i as u32 / frame.width);
+            // Diagonal stripes, so they stand out from anything in the picture.
+            if luma(px) >= threshold && (x + y) / STRIPE_WIDTH % 2 == 0 {
+                pixels[i * 4..i * 4 + 4].copy_from_slice(&[0, 0, 0, 255]);
+            }
+        }
+    }
+
+    /// Marks pixels where the luma gradient is steep, which is where the picture is sharp.
+    fn draw_peaking(&self, frame: &Frame, pixels: &mut [u8]) {
+        let (width, height) = (frame.width as usize, frame.height as usize);
+        if width < 2 || height < 2 {
+            return;
+        }
+        let lumas: Vec<i32> = frame.pixels.chunks_exact(4).map(|px| luma(px) as i32).collect();
+        let threshold = self.peaking_level as i32;
+        let color = self.peaking_color.rgba();
+        for y in 0..height - 1 {
+            for x in 0..width - 1 {
+                let i = y * width + x;
+                let Some(center) = lumas.get(i) else {
+                    return;
+                };
+                let dx = lumas.get(i + 1).map_or(0, |right| (right - center).abs());
+                let dy = lumas.get(i + width).map_or(0, |below| (below - center).abs());
+                if dx + dy > threshold {
+                    pixels[i * 4..i * 4 + 4].copy_from_slice(&color);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AspectRatio {
+    Wide,
+    Standard,
+    Square,
+    Vertical,
+}
+
+impl AspectRatio {
+    pub const ALL: [AspectRatio; 4] = [
+        AspectRatio::Wide,
+        AspectRatio::Standard,
+        AspectRatio::Square,
+        AspectRatio::Vertical,
+    ];
+
+    fn ratio(&self) -> (u32, u32) {
+        match self {
+            AspectRatio::Wide => (16, 9),
+            AspectRatio::Standard => (4, 3),
+            AspectRatio::Square => (1, 1),
+            AspectRatio::Vertical => (9, 16),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        AspectRatio::ALL.into_iter().find(|aspect| aspect.as_ref() == name)
+    }
+}
+
+impl AsRef<str> for AspectRatio {
+    fn as_ref(&self) -> &str {
+        match self {
+            AspectRatio::Wide => "16:9",
+            AspectRatio::Standard => "4:3",
+            AspectRatio::Square => "1:1",
+            AspectRatio::Vertical => "9:16",
+        }
+    }
+}
+
+/// Framing guides drawn over the preview, saved per device.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Guides {
+    pub thirds: bool,
+    pub center: bool,
+    pub safe_area: bool,
+    /// Darkens what falls outside this aspect ratio.
+    pub aspect: Option<AspectRatio>,
+    /// Flips the preview only; the device's horizontal flip is left alone.
+    pub mirror: bool,
+}
+
+impl Guides {
+    /// Parses the comma separated form written by `to_config`, e.g. `thirds,aspect=16:9,mirror`.
+    pub fn from_config(value: &str) -> Self {
+        let mut guides = Guides::default();
+        for item in value.split(',').map(str::trim) {
+            match item {
+                "thirds" => guides.thirds = true,
+                "center" => guides.center = true,
+                "safe" => guides.safe_area = true,
+                "mirror" => guides.mirror = true,
+                item => {
+                    if let Some(aspect) = item.strip_prefix("aspect=") {
+                        guides.aspect = AspectRatio::from_name(aspect);
+                    }
+                }
+            }
+        }
+        guides
+    }
+
+    pub fn to_config(&self) -> String {
+        let mut items = Vec::new();
+        if self.thirds {
+            items.push(String::from("thirds"));
+        }
+        if self.center {
+            items.push(String::from("center"));
+        }
+        if self.safe_area {
+            items.push(String::from("safe"));
+        }
+        if let Some(aspect) = self.aspect {
+            items.push(format!("aspect={}", aspect.as_ref()));
+        }
+        if self.mirror {
+            items.push(String::from("mirror"));
+        }
+        items.join(",")
+    }
+
+    pub fn is_active(&self) -> bool {
+        *self != Guides::default()
+    }
+
+    /// Mirrors the pixels if asked to and draws the guides over them.
+    pub fn apply(&self, width: u32, height: u32, pixels: &mut [u8]) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let mut canvas = Canvas { width, height, pixels };
+        if self.mirror {
+            canvas.mirror();
+        }
+        let (left, top, right, bottom) = match self.aspect {
+            Some(aspect) => {
+                let area = canvas.aspect_area(aspect);
+                canvas.mask(area);
+                area
+            }
+            None => (0, 0, width, height),
+        };
+        let (area_width, area_height) = (right - left, bottom - top);
+        if self.thirds {
+            for third in [1, 2] {
+                canvas.vertical_line(left + area_width * third / 3, top, bottom);
+                canvas.horizontal_line(top + area_height * third / 3, left, right);
+            }
+        }
+        if self.safe_area {
+            // Action safe at 5% and title safe at 10% in from each edge.
+            for percent in [5, 10] {
+                let (dx, dy) = (area_width * percent / 100, area_height * percent / 100);
+                canvas.rectangle(left + dx, top + dy, right - dx, bottom - dy);
+            }
+        }
+        if self.center {
+            let (x, y) = (left + area_width / 2, top + area_height / 2);
+            let arm = area_width.min(area_height) / 20;
+            canvas.horizontal_line(y, x.saturating_sub(arm), x + arm);
+            canvas.vertical_line(x, y.saturating_sub(arm), y + arm);
+        }
+    }
+}
+
+/// RGBA pixels to draw guides on.
+struct Canvas<'a> {
+    width: u32,
+    height: u32,
+    pixels: &'a mut [u8],
+}
+
+impl Canvas<'_> {
+    /// Lightens a pixel halfway to white, keeping the picture visible underneath.
+    fn blend(&mut self, x: u32, y: u32) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let i = ((y * self.width + x) * 4) as usize;
+        if let Some(px) = self.pixels.get_mut(i..i + 3) {
+            px.iter_mut().for_each(|c| *c = (*c as u16 + 255).div_ceil(2) as u8);
+        }
+    }
+
+    fn horizontal_line(&mut self, y: u32, from: u32, to: u32) {
+        (from..to).for_each(|x| self.blend(x, y));
+    }
+
+    fn vertical_line(&mut self, x: u32, from: u32, to: u32) {
+        (from..to).for_each(|y| self.blend(x, y));
+    }
+
+    fn rectangle(&mut self, left: u32, top: u32, right: u32, bottom: u32) {
+        self.horizontal_line(top, left, right);
+        self.horizontal_line(bottom.saturating_sub(1), left, right);
+        self.vertical_line(left, top, bottom);
+        self.vertical_line(right.saturating_sub(1), top, bottom);
+    }
+
+    fn mirror(&mut self) {
+        let row_len = (self.width * 4) as usize;
+        for row in self.pixels.chunks_exact_mut(row_len) {
+            for x in 0..self.width as usize / 2 {
+                let mirrored = self.width as usize - 1 - x;
+                for c in 0..4 {
+                    row.swap(x * 4 + c, mirrored * 4 + c);
+                }
+            }
+        }
+    }
+
+    /// The largest centered area with the given aspect ratio.
+    fn aspect_area(&self, aspect: AspectRatio) -> (u32, u32, u32, u32) {
+        let (w, h) = aspect.ratio();
+        let (width, height) = (self.width as u64, self.height as u64);
+        if width * h as u64 > height * w as u64 {
+            let area_width = (height * w as u64 / h as u64) as u32;
+            let left = (self.width - area_width) / 2;
+            (left, 0, left + area_width, self.height)
+        } else {
+            let area_height = (width * h as u64 / w as u64) as u32;
+            let top = (self.height - area_height) / 2;
+            (0, top, self.width, top + area_height)
+        }
+    }
+
+    /// Darkens everything outside the area.
+    fn mask(&mut self, (left, top, right, bottom): (u32, u32, u32, u32)) {
+        for (i, px) in self.pixels.chunks_exact_mut(4).enumerate() {
+            let (x, y) = (i as u32 % self.width, i as u32 / self.width);
+            if x < left || x >= right || y < top || y >= bottom {
+                px[..3].iter_mut().for_each(|c| *c /= 4);
+            }
+        }
+    }
+}
@@ -0,0 +1,299 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::time::{Duration, Instant};
+
+use v4l::control::Value as ControlValue;
+
+use crate::cid;
+use crate::device::{find_control, ControlData, DeviceControls, IntegerControl};
+use crate::scopes::luma;
+use crate::stream::Frame;
+
+/// Every control the PTZ pad takes over from the generic form.
+pub const PTZ_CONTROLS: [u32; 11] = [
+    cid::PAN_ABSOLUTE,
+    cid::TILT_ABSOLUTE,
+    cid::ZOOM_ABSOLUTE,
+    cid::PAN_RELATIVE,
+    cid::TILT_RELATIVE,
+    cid::ZOOM_RELATIVE,
+    cid::PAN_SPEED,
+    cid::TILT_SPEED,
+    cid::ZOOM_CONTINUOUS,
+    cid::PAN_RESET,
+    cid::TILT_RESET,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    Pan,
+    Tilt,
+    Zoom,
+}
+
+/// The pan, tilt and zoom controls of a camera, by how they move it.
+#[derive(Debug, Clone, Copy)]
+pub struct Ptz<'a> {
+    absolute: [Option<&'a IntegerControl>; 3],
+    relative: [Option<&'a IntegerControl>; 3],
+    /// Keeps moving while non-zero: pan and tilt speed and continuous zoom.
+    speed: [Option<&'a IntegerControl>; 3],
+    pan_reset: Option<u32>,
+    tilt_reset: Option<u32>,
+}
+
+fn axis_index(axis: Axis) -> usize {
+    match axis {
+        Axis::Pan => 0,
+        Axis::Tilt => 1,
+        Axis::Zoom => 2,
+    }
+}
+
+impl<'a> Ptz<'a> {
+    /// Finds the PTZ controls of a device, `None` if it has none.
+    pub fn find(controls: &'a [DeviceControls]) -> Option<Self> {
+        let integer = |id| match find_control(controls, id) {
+            Some(DeviceControls::Integer(control)) if !control.is_disabled() => Some(control),
+            _ => None,
+        };
+        let button = |id| match find_control(controls, id) {
+            Some(DeviceControls::Button(control)) if !control.is_disabled() => Some(control.id),
+            _ => None,
+        };
+        let ptz = Ptz {
+            absolute: [integer(cid::PAN_ABSOLUTE), integer(cid::TILT_ABSOLUTE), integer(cid::ZOOM_ABSOLUTE)],
+            relative: [integer(cid::PAN_RELATIVE), integer(cid::TILT_RELATIVE), integer(cid::ZOOM_RELATIVE)],
+            speed: [integer(cid::PAN_SPEED), integer(cid::TILT_SPEED), integer(cid::ZOOM_CONTINUOUS)],
+            pan_reset: button(cid::PAN_RESET),
+            tilt_reset: button(cid::TILT_RESET),
+        };
+        let any = [Axis::Pan, Axis::Tilt, Axis::Zoom].iter().any(|axis| ptz.can_move(*axis));
+        any.then_some(ptz)
+    }
+
+    pub fn can_move(&self, axis: Axis) -> bool {
+        let i = axis_index(axis);
+        self.absolute[i].is_some() || self.relative[i].is_some() || self.speed[i].is_some()
+    }
+
+    pub fn absolute(&self, axis: Axis) -> Option<&'a IntegerControl> {
+        self.absolute[axis_index(axis)]
+    }
+
+    /// The write that starts moving along an axis, `direction` being 1 or -1.
+    /// Speed controls keep going until `stop`; the others move a single step,
+    /// `current` giving the shown value of absolute controls.
+    pub fn start(&self, axis: Axis, direction: i64, current: impl Fn(&IntegerControl) -> i64) -> Option<(u32, ControlValue)> {
+        let i = axis_index(axis);
+        if let Some(control) = self.speed[i] {
+            // Half the top speed, which is easier to aim with.
+            let speed = ((control.max.max(-control.min) + 1) / 2).max(1);
+            return Some((control.id, ControlValue::Integer(control.snap(direction * speed))));
+        }
+        if let Some(control) = self.relative[i] {
+            return Some((control.id, ControlValue::Integer(control.snap(direction * control.coarse_step() / 2))));
+        }
+        let control = self.absolute[i]?;
+        Some((control.id, ControlValue::Integer(control.nudge(current(control), direction, true))))
+    }
+
+    /// The write that stops a move started with `start`, if one is needed.
+    pub fn stop(&self, axis: Axis) -> Option<(u32, ControlValue)> {
+        let control = self.speed[axis_index(axis)]?;
+        Some((control.id, ControlValue::Integer(0)))
+    }
+
+    /// Points the camera straight ahead: through the reset buttons where the
+    /// device has them, otherwise by restoring the default pan and tilt.
+    pub fn home(&self) -> Vec<(u32, ControlValue)> {
+        let mut writes = Vec::new();
+        let resets = [(Axis::Pan, self.pan_reset), (Axis::Tilt, self.tilt_reset)];
+        for (axis, reset) in resets {
+            match (reset, self.absolute(axis)) {
+                (Some(id), _) => writes.push((id, ControlValue::None)),
+                (None, Some(control)) => writes.push((control.id, ControlValue::Integer(control.default))),
+                (None, None) => (),
+            }
+        }
+        writes
+    }
+}
+
+/// Controls saved in a position preset, where the device has them.
+pub const PRESET_CONTROLS: [u32; 4] = [
+    cid::PAN_ABSOLUTE,
+    cid::TILT_ABSOLUTE,
+    cid::ZOOM_ABSOLUTE,
+    cid::FOCUS_ABSOLUTE,
+];
+
+/// A named camera position: absolute pan, tilt, zoom and focus values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preset {
+    pub name: String,
+    pub values: Vec<(u32, i64)>,
+}
+
+/// A smooth move from one position to another.
+#[derive(Debug, Clone)]
+pub struct Transition {
+    from: Vec<(u32, i64)>,
+    to: Vec<(u32, i64)>,
+    started: Instant,
+    duration: Duration,
+}
+
+impl Transition {
+    pub fn new(from: Vec<(u32, i64)>, to: Vec<(u32, i64)>, duration: Duration) -> Self {
+        Self { from, to, started: Instant::now(), duration }
+    }
+
+    /// The values to write now, eased in and out, and whether the move is done.
+    pub fn step(&self) -> (Vec<(u32, i64)>, bool) {
+        let t = if self.duration.is_zero() {
+            1.0
+        } else {
+            (self.started.elapsed().as_secs_f64() / self.duration.as_secs_f64()).min(1.0)
+        };
+        let eased = t * t * (3.0 - 2.0 * t);
+        let values = self.to.iter().map(|(id, to)| {
+            let from = self.from.iter().find(|(from_id, _)| from_id == id).map_or(*to, |(_, from)| *from);
+            (*id, from + ((to - from) as f64 * eased).round() as i64)
+        }).collect();
+        (values, t >= 1.0)
+    }
+}
+
+/// Horizontal field of view at the widest zoom, in degrees, until calibrated.
+pub const DEFAULT_FOV: f32 = 70.0;
+/// Assumed zoom range when the zoom control's units say nothing about it.
+const MAX_ZOOM_RATIO: f32 = 10.0;
+/// Pan and tilt absolute are in arc seconds.
+const ARC_SECONDS: f32 = 3600.0;
+/// Columns kept when comparing frames during calibration.
+const PROFILE_WIDTH: usize = 320;
+
+impl Ptz<'_> {
+    /// How much the current zoom narrows the view compared to the widest.
+    /// The zoom units are meant to follow the focal length, so a ratio to the
+    /// minimum works when it isn't zero.
+    pub fn magnification(&self, zoom: Option<i64>) -> f32 {
+        let (Some(control), Some(zoom)) = (self.absolute(Axis::Zoom), zoom) else {
+            return 1.0;
+        };
+        if control.min > 0 {
+            return zoom as f32 / control.min as f32;
+        }
+        let range = (control.max - control.min).max(1) as f32;
+        1.0 + (zoom - control.min) as f32 / range * (MAX_ZOOM_RATIO - 1.0)
+    }
+
+    /// Pan and tilt values that center a point of the picture, given as
+    /// fractions of its size from the center, -0.5 to 0.5 with y down.
+    /// `fov` is the horizontal field of view at the widest zoom.
+    pub fn aim(&self, fov: f32, aspect: f32, (x, y): (f32, f32), current: impl Fn(&IntegerControl) -> i64) -> Vec<(u32, i64)> {
+        let zoom = self.absolute(Axis::Zoom).map(&current);
+        let horizontal = fov / self.magnification(zoom);
+        let vertical = horizontal / aspect;
+        let mut values = Vec::new();
+        if let Some(pan) = self.absolute(Axis::Pan) {
+            let delta = (x * horizontal * ARC_SECONDS) as i64;
+            values.push((pan.id, pan.snap(current(pan) + delta)));
+        }
+        if let Some(tilt) = self.absolute(Axis::Tilt) {
+            // Positive tilt looks up.
+            let delta = (-y * vertical * ARC_SECONDS) as i64;
+            values.push((tilt.id, tilt.snap(current(tilt) + delta)));
+        }
+        values
+    }
+
+    /// Zoom position after scrolling by `lines`, in steps of a fortieth of the range.
+    pub fn scroll_zoom(&self, lines: f32, current: impl Fn(&IntegerControl) -> i64) -> Option<(u32, i64)> {
+        let control = self.absolute(Axis::Zoom)?;
+        let steps = ((control.max - control.min) / control.step.max(1) as i64 / 40).max(1);
+        let direction = if lines > 0.0 { 1 } else { -1 };
+        Some((control.id, control.nudge(current(control), direction * steps, false)))
+    }
+}
+
+impl Ptz<'_> {
+    /// Where to pan to for calibrating, and by how many degrees that is:
+    /// a share of the estimated view, to the right unless that's out of range.
+    pub fn calibration_move(&self, fov: f32, pan: i64, zoom: Option<i64>) -> Option<(i64, f32)> {
+        let control = self.absolute(Axis::Pan)?;
+        let delta = (fov / self.magnification(zoom) * CALIBRATION_SHARE * ARC_SECONDS) as i64;
+        let target = match pan + delta <= control.max {
+            true => control.snap(pan + delta),
+            false => control.snap(pan - delta),
+        };
+        (target != pan).then(|| (target, (target - pan) as f32 / ARC_SECONDS))
+    }
+
+    /// The field of view at the widest zoom, from how many pixels the picture
+    /// moved when panning by `degrees`.
+    pub fn calibrated_fov(&self, degrees: f32, shift: f32, width: u32, zoom: Option<i64>) -> Option<f32> {
+        if shift == 0.0 || shift.signum() != degrees.signum() {
+            return None;
+        }
+        let horizontal = degrees / shift * width as f32;
+        Some(horizontal * self.magnification(zoom)).filter(|fov| (1.0..=180.0).contains(fov))
+    }
+}
+
+/// A step of calibrating the field of view by panning and seeing how far the picture moves.
+#[derive(Debug, Clone)]
+pub enum Calibration {
+    /// Waiting for a frame to compare against.
+    Start,
+    /// Panned by `degrees` from `pan`; waiting for the camera to settle.
+    Moving {
+        pan: i64,
+        degrees: f32,
+        before: Vec<f32>,
+        until: Instant,
+    },
+}
+
+/// How far to pan while calibrating, as a share of the estimated view.
+const CALIBRATION_SHARE: f32 = 0.15;
+/// Time given to the camera to finish moving before comparing.
+pub const CALIBRATION_SETTLE: Duration = Duration::from_millis(1500);
+
+/// Mean luma of each column, scaled down to at most `PROFILE_WIDTH` columns.
+pub fn column_profile(frame: &Frame) -> Vec<f32> {
+    let width = frame.width as usize;
+    let columns = width.min(PROFILE_WIDTH).max(1);
+    let mut sums = vec![0.0f32; columns];
+    for (i, px) in frame.pixels.chunks_exact(4).enumerate() {
+        let column = (i % width.max(1)) * columns / width.max(1);
+        sums[column] += luma(px) as f32;
+    }
+    let per_column = (frame.height as usize * width / columns).max(1) as f32;
+    sums.iter().map(|sum| sum / per_column).collect()
+}
+
+/// How many frame pixels the picture moved left between two profiles, found
+/// as the offset with the smallest difference where they overlap.
+pub fn estimate_shift(before: &[f32], after: &[f32], frame_width: u32) -> Option<f32> {
+    let len = before.len().min(after.len()) as i64;
+    if len < 8 {
+        return None;
+    }
+    let mut best: Option<(i64, f32)> = None;
+    for shift in -len / 2..=len / 2 {
+        let overlap: Vec<f32> = (0..len)
+            .filter_map(|x| {
+                let from = x + shift;
+                (0..len).contains(&from).then(|| (before[from as usize] - after[x as usize]).abs())
+            })
+            .collect();
+        let error = overlap.iter().sum::<f32>() / overlap.len().max(1) as f32;
+        if best.map_or(true, |(_, best_error)| error < best_error) {
+            best = Some((shift, error));
+        }
+    }
+    let (shift, _) = best?;
+    (shift != 0).then(|| shift as f32 * frame_width as f32 / len as f32)
+}
@@ -0,0 +1,342 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use v4l::{Format, FourCC};
+
+use crate::snapshot;
+use crate::stream::{decode_frame, Frame};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RecordingFormat {
+    /// MJPEG in AVI when the camera delivers MJPEG, raw Y4M otherwise.
+    #[default]
+    Video,
+    ImageSequence,
+}
+
+impl RecordingFormat {
+    pub const ALL: [RecordingFormat; 2] = [RecordingFormat::Video, RecordingFormat::ImageSequence];
+}
+
+impl AsRef<str> for RecordingFormat {
+    fn as_ref(&self) -> &str {
+        match self {
+            RecordingFormat::Video => "Video (AVI/Y4M)",
+            RecordingFormat::ImageSequence => "Image sequence",
+        }
+    }
+}
+
+/// What to record, passed along with the stream.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Recording {
+    pub format: RecordingFormat,
+    pub folder: PathBuf,
+    /// Used to name the output, e.g. the device name.
+    pub name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordingStats {
+    pub output: PathBuf,
+    pub frames: u64,
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+/// Writes captured frames to disk in the chosen format.
+pub struct Recorder {
+    writer: Writer,
+    output: PathBuf,
+    started: Instant,
+    frames: u64,
+    bytes: u64,
+}
+
+enum Writer {
+    Avi(AviWriter),
+    Y4m(Y4mWriter),
+    Images(ImageWriter),
+}
+
+impl Recorder {
+    /// `fps` is what the device is configured for and only ends up in headers.
+    pub fn new(recording: &Recording, format: &Format, fps: (u32, u32)) -> Result<Self, String> {
+        fs::create_dir_all(&recording.folder).map_err(|e| format!("{}", e))?;
+        let stem = format!(
+            "{}-{}",
+            recording.name.replace([' ', '/'], "_"),
+            chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        );
+        let base = recording.folder.join(stem);
+        let (writer, output) = match recording.format {
+            RecordingFormat::Video if &format.fourcc.repr == b"MJPG" => {
+                let output = base.with_extension("avi");
+                (Writer::Avi(AviWriter::new(&output, format.width, format.height, fps)?), output)
+            }
+            RecordingFormat::Video => {
+                let output = base.with_extension("y4m");
+                (Writer::Y4m(Y4mWriter::new(&output, format, fps)?), output)
+            }
+            RecordingFormat::ImageSequence => {
+                fs::create_dir_all(&base).map_err(|e| format!("{}", e))?;
+                (Writer::Images(ImageWriter { folder: base.clone(), index: 0 }), base)
+            }
+        };
+        Ok(Self {
+            writer,
+            output,
+            started: Instant::now(),
+            frames: 0,
+            bytes: 0,
+        })
+    }
+
+    /// Writes one frame as the device delivered it.
+    pub fn write(&mut self, format: &Format, data: &[u8]) -> Result<(), String> {
+        self.bytes += match &mut self.writer {
+            Writer::Avi(writer) => writer.write(data).map_err(|e| format!("{}", e))?,
+            Writer::Y4m(writer) => writer.write(format, data)?,
+            Writer::Images(writer) => writer.write(format, data)?,
+        };
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn stats(&self) -> RecordingStats {
+        RecordingStats {
+            output: self.output.clone(),
+            frames: self.frames,
+            bytes: self.bytes,
+            elapsed: self.started.elapsed(),
+        }
+    }
+
+    /// Completes the file, which for AVI means writing the index and frame counts.
+    pub fn finish(self) -> Result<RecordingStats, String> {
+        let stats = self.stats();
+        match self.writer {
+            Writer::Avi(writer) => writer.finish(stats.elapsed).map_err(|e| format!("{}", e))?,
+            Writer::Y4m(mut writer) => writer.file.flush().map_err(|e| format!("{}", e))?,
+            Writer::Images(_) => (),
+        }
+        Ok(stats)
+    }
+}
+
+/// A minimal AVI 1.0 writer for a single MJPEG video stream.
+struct AviWriter {
+    file: BufWriter<File>,
+    width: u32,
+    height: u32,
+    /// Offset and size of each frame chunk, relative to the `movi` list.
+    index: Vec<(u32, u32)>,
+    max_frame: u32,
+    /// Bytes of frame chunks written so far.
+    movi_size: u32,
+}
+
+const AVI_HEADER_SIZE: u64 = 224;
+
+impl AviWriter {
+    fn new(path: &Path, width: u32, height: u32, fps: (u32, u32)) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("{}", e))?;
+        let mut writer = Self {
+            file: BufWriter::new(file),
+            width,
+            height,
+            index: Vec::new(),
+            max_frame: 0,
+            movi_size: 0,
+        };
+        // Written again with the real counts when finishing.
+        writer.write_headers(0, fps).map_err(|e| format!("{}", e))?;
+        Ok(writer)
+    }
+
+    fn write_headers(&mut self, movi_size: u32, fps: (u32, u32)) -> std::io::Result<()> {
+        let (rate, scale) = (fps.0.max(1), fps.1.max(1));
+        let frames = self.index.len() as u32;
+        let idx_size = 8 + 16 * frames;
+        let riff_size = (AVI_HEADER_SIZE - 8) as u32 + movi_size + idx_size;
+        let f = &mut self.file;
+        f.write_all(b"RIFF")?;
+        f.write_all(&riff_size.to_le_bytes())?;
+        f.write_all(b"AVI ")?;
+
+        f.write_all(b"LIST")?;
+        f.write_all(&192u32.to_le_bytes())?;
+        f.write_all(b"hdrl")?;
+        f.write_all(b"avih")?;
+        f.write_all(&56u32.to_le_bytes())?;
+        for value in [
+            (1_000_000u64 * scale as u64 / rate as u64) as u32, // microseconds per frame
+            0,                                                  // max bytes per second
+            0,                                                  // padding granularity
+            0x10,                                               // AVIF_HASINDEX
+            frames,
+            0,                                                  // initial frames
+            1,                                                  // streams
+            self.max_frame,
+            self.width,
+            self.height,
+            0, 0, 0, 0,
+        ] {
+            f.write_all(&value.to_le_bytes())?;
+        }
+
+        f.write_all(b"LIST")?;
+        f.write_all(&116u32.to_le_bytes())?;
+        f.write_all(b"strl")?;
+        f.write_all(b"strh")?;
+        f.write_all(&56u32.to_le_bytes())?;
+        f.write_all(b"vids")?;
+        f.write_all(b"MJPG")?;
+        for value in [0u32, 0, 0, scale, rate, 0, frames, self.max_frame, u32::MAX, 0] {
+            f.write_all(&value.to_le_bytes())?;
+        }
+        for value in [0u16, 0, self.width as u16, self.height as u16] {
+            f.write_all(&value.to_le_bytes())?;
+        }
+        f.write_all(b"strf")?;
+        f.write_all(&40u32.to_le_bytes())?;
+        f.write_all(&40u32.to_le_bytes())?;
+        f.write_all(&(self.width as i32).to_le_bytes())?;
+        f.write_all(&(self.height as i32).to_le_bytes())?;
+        f.write_all(&1u16.to_le_bytes())?;
+        f.write_all(&24u16.to_le_bytes())?;
+        f.write_all(b"MJPG")?;
+        f.write_all(&(self.width * self.height * 3).to_le_bytes())?;
+        for value in [0u32; 4] {
+            f.write_all(&value.to_le_bytes())?;
+        }
+
+        f.write_all(b"LIST")?;
+        f.write_all(&(movi_size + 4).to_le_bytes())?;
+        f.write_all(b"movi")
+    }
+
+    fn write(&mut self, data: &[u8]) -> std::io::Result<u64> {
+        let size = data.len() as u32;
+        // Offsets count from the `movi` fourcc; chunks are padded to even sizes.
+        let offset = self.movi_size + 4;
+        self.file.write_all(b"00dc")?;
+        self.file.write_all(&size.to_le_bytes())?;
+        self.file.write_all(data)?;
+        if size % 2 == 1 {
+            self.file.write_all(&[0])?;
+        }
+        self.index.push((offset, size));
+        self.max_frame = self.max_frame.max(size);
+        self.movi_size += 8 + (size + 1) / 2 * 2;
+        Ok(8 + data.len() as u64)
+    }
+
+    fn finish(mut self, elapsed: Duration) -> std::io::Result<()> {
+        self.file.write_all(b"idx1")?;
+        self.file.write_all(&(16 * self.index.len() as u32).to_le_bytes())?;
+        for (offset, size) in self.index.clone() {
+            self.file.write_all(b"00dc")?;
+            self.file.write_all(&0x10u32.to_le_bytes())?; // AVIIF_KEYFRAME
+            self.file.write_all(&offset.to_le_bytes())?;
+            self.file.write_all(&size.to_le_bytes())?;
+        }
+
+        // Store the rate that was actually recorded, in milliframes per second.
+        let millis = elapsed.as_millis().max(1) as u64;
+        let rate = (self.index.len() as u64 * 1_000_000 / millis).max(1) as u32;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.write_headers(self.movi_size, (rate, 1000))?;
+        self.file.flush()
+    }
+}
+
+/// Uncompressed YUV4MPEG2, 4:2:2 straight from YUYV and 4:4:4 for anything else.
+struct Y4mWriter {
+    file: BufWriter<File>,
+}
+
+impl Y4mWriter {
+    fn new(path: &Path, format: &Format, fps: (u32, u32)) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("{}", e))?;
+        let mut file = BufWriter::new(file);
+        let chroma = if format.fourcc == FourCC::new(b"YUYV") { "422" } else { "444" };
+        writeln!(
+            file,
+            "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C{}",
+            format.width, format.height, fps.0.max(1), fps.1.max(1), chroma,
+        ).map_err(|e| format!("{}", e))?;
+        Ok(Self { file })
+    }
+
+    fn write(&mut self, format: &Format, data: &[u8]) -> Result<u64, String> {
+        let planes = if format.fourcc == FourCC::new(b"YUYV") {
+            yuyv_planes(format, data)?
+        } else {
+            rgba_planes(&decode_frame(format, data)?)
+        };
+        self.file.write_all(b"FRAME\n").map_err(|e| format!("{}", e))?;
+        self.file.write_all(&planes).map_err(|e| format!("{}", e))?;
+        Ok(6 + planes.len() as u64)
+    }
+}
+
+/// Splits packed YUYV into Y, U and V planes.
+fn yuyv_planes(format: &Format, data: &[u8]) -> Result<Vec<u8>, String> {
+    let (width, height) = (format.width as usize, format.height as usize);
+    let stride = if format.stride == 0 { width * 2 } else { format.stride as usize };
+    if data.len() < stride * height {
+        return Err(String::from("Short YUYV frame"));
+    }
+    let mut y = Vec::with_capacity(width * height);
+    let mut u = Vec::with_capacity(width * height / 2);
+    let mut v = Vec::with_capacity(width * height / 2);
+    for row in data.chunks_exact(stride).take(height) {
+        for yuyv in row[..width * 2].chunks_exact(4) {
+            y.extend_from_slice(&[yuyv[0], yuyv[2]]);
+            u.push(yuyv[1]);
+            v.push(yuyv[3]);
+        }
+    }
+    y.append(&mut u);
+    y.append(&mut v);
+    Ok(y)
+}
+
+/// BT.601 limited range, the inverse of the preview conversion.
+fn rgba_planes(frame: &Frame) -> Vec<u8> {
+    let count = (frame.width * frame.height) as usize;
+    let mut planes = vec![0; count * 3];
+    for (i, px) in frame.pixels.chunks_exact(4).take(count).enumerate() {
+        let (r, g, b) = (px[0] as i32, px[1] as i32, px[2] as i32);
+        planes[i] = (((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8;
+        planes[count + i] = (((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8;
+        planes[2 * count + i] = (((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8;
+    }
+    planes
+}
+
+/// Numbered stills in a folder: JPEG as delivered by MJPEG cameras, PNG otherwise.
+struct ImageWriter {
+    folder: PathBuf,
+    index: u64,
+}
+
+impl ImageWriter {
+    fn write(&mut self, format: &Format, data: &[u8]) -> Result<u64, String> {
+        self.index += 1;
+        let name = format!("frame-{:06}", self.index);
+        if &format.fourcc.repr == b"MJPG" {
+            fs::write(self.folder.join(name).with_extension("jpg"), data)
+                .map_err(|e| format!("{}", e))?;
+            return Ok(data.len() as u64);
+        }
+        let path = self.folder.join(name).with_extension("png");
+        snapshot::write_png(&path, &decode_frame(format, data)?, &[])?;
+        Ok(fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0))
+    }
+}
@@ -0,0 +1,224 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::device::blocking;
+use crate::fl;
+use crate::stream::Frame;
+
+/// Pixels sampled along each axis at most; scopes don't need every pixel.
+const MAX_SAMPLES: u32 = 320;
+const SCOPE_HEIGHT: u32 = 192;
+const BACKGROUND: [u8; 4] = [16, 16, 16, 255];
+const GRATICULE: [u8; 4] = [70, 70, 70, 255];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScopeKind {
+    Histogram,
+    Parade,
+    Waveform,
+    Vectorscope,
+}
+
+impl ScopeKind {
+    pub const ALL: [ScopeKind; 4] = [
+        ScopeKind::Histogram,
+        ScopeKind::Parade,
+        ScopeKind::Waveform,
+        ScopeKind::Vectorscope,
+    ];
+
+    pub fn title(&self) -> String {
+        match self {
+            ScopeKind::Histogram => fl!("scope-histogram"),
+            ScopeKind::Parade => fl!("scope-parade"),
+            ScopeKind::Waveform => fl!("scope-waveform"),
+            ScopeKind::Vectorscope => fl!("scope-vectorscope"),
+        }
+    }
+}
+
+/// An RGBA image of a scope, ready to be shown.
+#[derive(Debug, Clone)]
+pub struct ScopeImage {
+    pub kind: ScopeKind,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl ScopeImage {
+    fn new(kind: ScopeKind, width: u32, height: u32) -> Self {
+        Self {
+            kind,
+            width,
+            height,
+            pixels: BACKGROUND.repeat((width * height) as usize),
+        }
+    }
+
+    fn set(&mut self, x: u32, y: u32, color: [u8; 4]) {
+        if x < self.width && y < self.height {
+            let i = ((y * self.width + x) * 4) as usize;
+            self.pixels[i..i + 4].copy_from_slice(&color);
+        }
+    }
+
+    /// Draws a horizontal line at each given fraction of the height.
+    fn graticule(&mut self, levels: &[f32]) {
+        for level in levels {
+            let y = ((1.0 - level) * (self.height - 1) as f32).round() as u32;
+            for x in 0..self.width {
+                self.set(x, y, GRATICULE);
+            }
+        }
+    }
+}
+
+/// Renders the given scopes off the UI thread.
+pub async fn compute_scopes(frame: Frame, kinds: Vec<ScopeKind>) -> Result<Vec<ScopeImage>, String> {
+    blocking(move || Ok(kinds.into_iter().map(|kind| render(kind, &frame)).collect())).await
+}
+
+pub fn render(kind: ScopeKind, frame: &Frame) -> ScopeImage {
+    match kind {
+        ScopeKind::Histogram => histogram(frame),
+        ScopeKind::Parade => parade(frame),
+        ScopeKind::Waveform => waveform(frame),
+        ScopeKind::Vectorscope => vectorscope(frame),
+    }
+}
+
+/// BT.601 luma.
+pub fn luma(px: &[u8]) -> u8 {
+    ((px[0] as u32 * 299 + px[1] as u32 * 587 + px[2] as u32 * 114) / 1000) as u8
+}
+
+/// Calls `f` with the column and pixel for an evenly spaced subset of the frame.
+fn sample(frame: &Frame, mut f: impl FnMut(u32, &[u8])) {
+    let step_x = (frame.width / MAX_SAMPLES).max(1);
+    let step_y = (frame.height / MAX_SAMPLES).max(1);
+    for y in (0..frame.height).step_by(step_y as usize) {
+        for x in (0..frame.width).step_by(step_x as usize) {
+            let i = ((y * frame.width + x) * 4) as usize;
+            if let Some(px) = frame.pixels.get(i..i + 4) {
+                f(x, px);
+            }
+        }
+    }
+}
+
+/// Maps hit counts to brightness on a log scale so sparse traces stay visible.
+fn intensity(count: u32, max: u32) -> u8 {
+    if count == 0 {
+        return 0;
+    }
+    let level = (count as f32).ln_1p() / (max.max(1) as f32).ln_1p();
+    (64.0 + 191.0 * level).min(255.0) as u8
+}
+
+fn histogram(frame: &Frame) -> ScopeImage {
+    let mut bins = [0u32; 256];
+    sample(frame, |_, px| bins[luma(px) as usize] += 1);
+    let max = bins.iter().copied().max().unwrap_or(0).max(1);
+
+    let mut image = ScopeImage::new(ScopeKind::Histogram, 256, SCOPE_HEIGHT);
+    for (x, count) in bins.iter().enumerate() {
+        let bar = (*count as u64 * SCOPE_HEIGHT as u64 / max as u64) as u32;
+        for y in SCOPE_HEIGHT - bar..SCOPE_HEIGHT {
+            image.set(x as u32, y, [220, 220, 220, 255]);
+        }
+    }
+    // Clipping at either end is what we are looking for.
+    for (x, count) in [(0, bins[0]), (255, bins[255])] {
+        if count > 0 {
+            for y in 0..SCOPE_HEIGHT {
+                image.set(x, y, [230, 60, 60, 255]);
+            }
+        }
+    }
+    image
+}
+
+/// Columns of the frame map to columns of the scope, levels to height.
+fn trace(frame: &Frame, columns: u32, channel: impl Fn(&[u8]) -> u8) -> Vec<u32> {
+    let mut counts = vec![0u32; (columns * 256) as usize];
+    sample(frame, |x, px| {
+        let column = (x as u64 * columns as u64 / frame.width.max(1) as u64) as u32;
+        counts[(channel(px) as u32 * columns + column) as usize] += 1;
+    });
+    counts
+}
+
+fn draw_trace(image: &mut ScopeImage, counts: &[u32], columns: u32, offset: u32, color: [u8; 3]) {
+    let max = counts.iter().copied().max().unwrap_or(0);
+    for (i, count) in counts.iter().enumerate() {
+        if *count == 0 {
+            continue;
+        }
+        let (level, column) = (i as u32 / columns, i as u32 % columns);
+        let y = (255 - level) * (image.height - 1) / 255;
+        let value = intensity(*count, max) as u32;
+        let scale = |c: u8| (c as u32 * value / 255) as u8;
+        image.set(offset + column, y, [scale(color[0]), scale(color[1]), scale(color[2]), 255]);
+    }
+}
+
+fn waveform(frame: &Frame) -> ScopeImage {
+    let columns = 256;
+    let mut image = ScopeImage::new(ScopeKind::Waveform, columns, SCOPE_HEIGHT);
+    image.graticule(&[0.0, 0.25, 0.5, 0.75, 1.0]);
+    let counts = trace(frame, columns, luma);
+    draw_trace(&mut image, &counts, columns, 0, [255, 255, 255]);
+    image
+}
+
+fn parade(frame: &Frame) -> ScopeImage {
+    let columns = 128;
+    let mut image = ScopeImage::new(ScopeKind::Parade, columns * 3, SCOPE_HEIGHT);
+    image.graticule(&[0.0, 0.25, 0.5, 0.75, 1.0]);
+    let colors = [[255, 80, 80], [80, 255, 80], [100, 140, 255]];
+    for (channel, color) in colors.into_iter().enumerate() {
+        let counts = trace(frame, columns, |px| px[channel]);
+        draw_trace(&mut image, &counts, columns, channel as u32 * columns, color);
+    }
+    image
+}
+
+fn vectorscope(frame: &Frame) -> ScopeImage {
+    let size = SCOPE_HEIGHT;
+    let mut image = ScopeImage::new(ScopeKind::Vectorscope, size, size);
+    let center = (size / 2) as f32;
+    let radius = center - 1.0;
+
+    // Outer circle, axes and the skin tone line at about 123°.
+    for step in 0..720 {
+        let angle = (step as f32 / 2.0).to_radians();
+        image.set((center + radius * angle.cos()) as u32, (center - radius * angle.sin()) as u32, GRATICULE);
+    }
+    for i in 0..size {
+        image.set(i, center as u32, GRATICULE);
+        image.set(center as u32, i, GRATICULE);
+    }
+    let skin = 123f32.to_radians();
+    for r in 0..radius as u32 {
+        let r = r as f32;
+        image.set((center + r * skin.cos()) as u32, (center - r * skin.sin()) as u32, [120, 90, 60, 255]);
+    }
+
+    let mut counts = vec![0u32; (size * size) as usize];
+    sample(frame, |_, px| {
+        let (r, g, b) = (px[0] as f32, px[1] as f32, px[2] as f32);
+        let cb = -0.1687 * r - 0.3313 * g + 0.5 * b;
+        let cr = 0.5 * r - 0.4187 * g - 0.0813 * b;
+        let x = (center + cb / 128.0 * radius).clamp(0.0, (size - 1) as f32) as u32;
+        let y = (center - cr / 128.0 * radius).clamp(0.0, (size - 1) as f32) as u32;
+        counts[(y * size + x) as usize] += 1;
+    });
+    let max = counts.iter().copied().max().unwrap_or(0);
+    for (i, count) in counts.iter().enumerate() {
+        if *count > 0 {
+            let value = intensity(*count, max);
+            image.set(i as u32 % size, i as u32 / size, [value / 3, value, value / 3, 255]);
+        }
+    }
+    image
+}
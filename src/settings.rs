@@ -1,11 +1,85 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
 use cosmic::{cosmic_theme, theme, widget, Element};
 
-use crate::{app::{App, Message as AppMessage}, fl};
+use crate::{app::{App, MenuAction, Message as AppMessage}, fl, key_binds};
+use crate::auto::AutoSettings;
+use crate::overlays::Guides;
+
+/// Choices for how often volatile controls are read back, in milliseconds; 0 turns polling off.
+pub const POLL_INTERVALS: [u64; 5] = [0, 250, 500, 1000, 2000];
+/// Choices for how long recalling a PTZ preset takes, in milliseconds; 0 jumps straight there.
+pub const PRESET_TRANSITIONS: [u64; 5] = [0, 500, 1000, 2000, 4000];
+/// Choices for how often the scopes are redrawn, in milliseconds.
+pub const SCOPE_INTERVALS: [u64; 4] = [100, 250, 500, 1000];
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub key_binds: HashMap<MenuAction, String>,
+    pub poll_interval: u64,
+    pub scope_interval: u64,
+    pub preset_transition: u64,
+    /// Where recordings go; empty for the videos folder.
+    pub recording_folder: String,
+    /// Framing guides by device id.
+    pub guides: HashMap<String, Guides>,
+    /// Calibrated PTZ field of view at the widest zoom, in degrees, by device id.
+    pub fov: HashMap<String, f32>,
+    /// Software auto exposure and white balance by device id.
+    pub auto: HashMap<String, AutoSettings>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            key_binds: key_binds::default_key_binds(),
+            poll_interval: 500,
+            scope_interval: 250,
+            preset_transition: 1000,
+            recording_folder: String::new(),
+            guides: HashMap::new(),
+            fov: HashMap::new(),
+            auto: HashMap::new(),
+        }
+    }
+}
+
+pub fn get_recording_folder(config: &Config) -> PathBuf {
+    if !config.recording_folder.trim().is_empty() {
+        return PathBuf::from(config.recording_folder.trim());
+    }
+    dirs::video_dir()
+        .or_else(|| dirs::home_dir().map(|home| home.join("Videos")))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+pub fn poll_interval_labels() -> Vec<String> {
+    POLL_INTERVALS.iter().map(|ms| match ms {
+        0 => fl!("poll-off"),
+        ms => fl!("poll-interval-ms", ms = ms.to_string()),
+    }).collect()
+}
+
+pub fn preset_transition_labels() -> Vec<String> {
+    PRESET_TRANSITIONS.iter().map(|ms| match ms {
+        0 => fl!("preset-jump"),
+        ms => fl!("poll-interval-ms", ms = ms.to_string()),
+    }).collect()
+}
 
+pub fn scope_interval_labels() -> Vec<String> {
+    SCOPE_INTERVALS.iter().map(|ms| fl!("poll-interval-ms", ms = ms.to_string())).collect()
+}
 
 #[derive(Debug, Clone)]
 pub enum Message {
     CloseToTray(bool),
+    KeyBind(MenuAction, String),
+    PollInterval(usize),
+    ScopeInterval(usize),
+    PresetTransition(usize),
+    RecordingFolder(String),
 }
 
 pub enum Task {
@@ -27,11 +101,87 @@ pub fn settings(&self) -> Element<AppMessage> {
             |val| { AppMessage::Setting(Message::CloseToTray(val))}
         );
 
+    let shortcuts = MenuAction::ALL.iter().fold(
+        widget::settings::section().title(fl!("shortcuts")),
+        |section, action| {
+            let action = *action;
+            let shortcut = self.shortcut_edits.get(&action)
+                .or(self.config.key_binds.get(&action))
+                .cloned()
+                .unwrap_or_default();
+            let valid = shortcut.is_empty() || key_binds::parse_key_bind(&shortcut).is_some();
+            let conflict = key_binds::conflict(&self.config.key_binds, action, &shortcut);
+            let input = widget::text_input(fl!("shortcut-none"), shortcut)
+                .on_input(move |val| AppMessage::Setting(Message::KeyBind(action, val)));
+            let mut item = widget::settings::item::builder(action.title());
+            if !valid {
+                item = item.description(fl!("shortcut-invalid"));
+            } else if let Some(other) = conflict {
+                item = item.description(fl!("shortcut-conflict", action = other.title()));
+            }
+            section.add(item.control(input))
+        },
+    );
+
+    let polling = widget::settings::section()
+        .title(fl!("live-controls"))
+        .add(
+            widget::settings::item::builder(fl!("poll-interval"))
+                .description(fl!("poll-interval-description"))
+                .control(widget::dropdown(
+                    &self.poll_interval_labels,
+                    POLL_INTERVALS.iter().position(|ms| *ms == self.config.poll_interval),
+                    |index| AppMessage::Setting(Message::PollInterval(index)),
+                ))
+        );
+
+    let scopes = widget::settings::section()
+        .title(fl!("scopes"))
+        .add(
+            widget::settings::item::builder(fl!("scope-interval"))
+                .description(fl!("scope-interval-description"))
+                .control(widget::dropdown(
+                    &self.scope_interval_labels,
+                    SCOPE_INTERVALS.iter().position(|ms| *ms == self.config.scope_interval),
+                    |index| AppMessage::Setting(Message::ScopeInterval(index)),
+                ))
+        );
+
+    let ptz = widget::settings::section()
+        .title(fl!("ptz"))
+        .add(
+            widget::settings::item::builder(fl!("preset-transition"))
+                .description(fl!("preset-transition-description"))
+                .control(widget::dropdown(
+                    &self.preset_transition_labels,
+                    PRESET_TRANSITIONS.iter().position(|ms| *ms == self.config.preset_transition),
+                    |index| AppMessage::Setting(Message::PresetTransition(index)),
+                ))
+        );
+
+    let recording = widget::settings::section()
+        .title(fl!("recording"))
+        .add(
+            widget::settings::item::builder(fl!("recording-folder"))
+                .description(fl!("recording-folder-description"))
+                .control(
+                    widget::text_input(
+                        get_recording_folder(&Config::default()).display().to_string(),
+                        &self.config.recording_folder,
+                    )
+                    .on_input(|val| AppMessage::Setting(Message::RecordingFolder(val)))
+                )
+        );
 
     widget::column()
         // .push(icon)
         .push(title)
          .push(checkbox)
+        .push(polling)
+        .push(scopes)
+        .push(ptz)
+        .push(recording)
+        .push(shortcuts)
         // .align_items(Alignment::Center)
         .spacing(space_xxs)
         .into()
@@ -42,6 +192,35 @@ pub fn update_settings(&mut self, message: Message) -> Option<Task> {
         Message::CloseToTray(_) => {
             None
         }
+        Message::KeyBind(action, shortcut) => {
+            let shortcut = shortcut.trim().to_string();
+            let valid = shortcut.is_empty() || key_binds::parse_key_bind(&shortcut).is_some();
+            if !valid || key_binds::conflict(&self.config.key_binds, action, &shortcut).is_some() {
+                // Kept out of the config so a clash is never saved or bound.
+                self.shortcut_edits.insert(action, shortcut);
+                return None;
+            }
+            self.shortcut_edits.remove(&action);
+            self.config.key_binds.insert(action, shortcut);
+            self.key_binds = key_binds::key_binds(&self.config.key_binds);
+            Some(Task::Save)
+        }
+        Message::PollInterval(index) => {
+            self.config.poll_interval = *POLL_INTERVALS.get(index)?;
+            Some(Task::Save)
+        }
+        Message::ScopeInterval(index) => {
+            self.config.scope_interval = *SCOPE_INTERVALS.get(index)?;
+            Some(Task::Save)
+        }
+        Message::PresetTransition(index) => {
+            self.config.preset_transition = *PRESET_TRANSITIONS.get(index)?;
+            Some(Task::Save)
+        }
+        Message::RecordingFolder(folder) => {
+            self.config.recording_folder = folder;
+            Some(Task::Save)
+        }
     }
 }
 
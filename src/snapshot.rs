@@ -0,0 +1,180 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use image::codecs::jpeg::JpegEncoder;
+use image::ColorType;
+use v4l::control::Value as ControlValue;
+
+use crate::device::{blocking, get_device_by_path, DeviceControls, VideoDevice};
+use crate::stream::{self, Frame};
+
+const JPEG_QUALITY: u8 = 92;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    #[default]
+    Png,
+    Jpeg,
+}
+
+impl SnapshotFormat {
+    pub const ALL: [SnapshotFormat; 2] = [SnapshotFormat::Png, SnapshotFormat::Jpeg];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SnapshotFormat::Png => "png",
+            SnapshotFormat::Jpeg => "jpg",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "png" => Some(SnapshotFormat::Png),
+            "jpg" | "jpeg" => Some(SnapshotFormat::Jpeg),
+            _ => None,
+        }
+    }
+}
+
+impl AsRef<str> for SnapshotFormat {
+    fn as_ref(&self) -> &str {
+        match self {
+            SnapshotFormat::Png => "PNG",
+            SnapshotFormat::Jpeg => "JPEG",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotRequest {
+    /// Frame size to capture at, `None` for the device's current one.
+    /// Leave it `None` when passing a `frame`, which is saved at its own size.
+    pub size: Option<(u32, u32)>,
+    pub format: SnapshotFormat,
+    /// A frame that was already captured, such as the preview's latest.
+    pub frame: Option<Frame>,
+    /// Folder to save to, the pictures folder when `None`.
+    pub folder: Option<PathBuf>,
+}
+
+/// Grabs a frame if needed and saves it with the device's control values as metadata.
+pub async fn take_snapshot(path: String, request: SnapshotRequest) -> Result<PathBuf, String> {
+    blocking(move || save_snapshot(&path, request)).await
+}
+
+pub fn save_snapshot(path: &str, request: SnapshotRequest) -> Result<PathBuf, String> {
+    let frame = match request.frame {
+        Some(frame) if request.size.is_some_and(|size| size != (frame.width, frame.height)) => {
+            return Err(String::from("The frame is not at the requested size"));
+        }
+        Some(frame) => frame,
+        None => stream::grab_frame(path, request.size)?,
+    };
+    // Read the controls after capturing, so the metadata matches the frame.
+    let device = get_device_by_path(path)?;
+    let folder = match request.folder {
+        Some(folder) => folder,
+        None => get_pictures_dir(),
+    };
+    fs::create_dir_all(&folder).map_err(|e| format!("{}", e))?;
+    let filename = folder.join(get_snapshot_filename(&device, request.format));
+    let metadata = get_metadata(&device);
+
+    match request.format {
+        SnapshotFormat::Png => write_png(&filename, &frame, &metadata)?,
+        SnapshotFormat::Jpeg => write_jpeg(&filename, &frame, &metadata)?,
+    }
+    Ok(filename)
+}
+
+pub fn get_pictures_dir() -> PathBuf {
+    dirs::picture_dir()
+        .or_else(|| dirs::home_dir().map(|home| home.join("Pictures")))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// A name like `HD_Pro_Webcam_C920-20241019-153012.345.png`.
+pub fn get_snapshot_filename(device: &VideoDevice, format: SnapshotFormat) -> String {
+    format!(
+        "{}-{}.{}",
+        device.name.replace([' ', '/'], "_"),
+        chrono::Local::now().format("%Y%m%d-%H%M%S%.3f"),
+        format.extension(),
+    )
+}
+
+/// Key and value pairs describing the device and its current control values.
+pub fn get_metadata(device: &VideoDevice) -> Vec<(String, String)> {
+    let mut controls = Vec::new();
+    describe_controls(&device.controls, &mut controls);
+    vec![
+        (String::from("Software"), String::from("cosmic-cam")),
+        (String::from("Source"), format!("{} ({})", device.name, device.capabilities.card)),
+        (String::from("Creation Time"), chrono::Local::now().to_rfc3339()),
+        (String::from("Comment"), controls.join("\n")),
+    ]
+}
+
+fn describe_controls(controls: &[DeviceControls], lines: &mut Vec<String>) {
+    for control in controls {
+        let value = match control {
+            DeviceControls::ControlGroup(group) => {
+                describe_controls(&group.controls, lines);
+                continue;
+            }
+            DeviceControls::Integer(control) => control.value.to_string(),
+            DeviceControls::Boolean(control) => control.value.to_string(),
+            DeviceControls::Menu(control) => control.value.unwrap_or(0).to_string(),
+            DeviceControls::Control(control) => match control.value {
+                ControlValue::Integer(val) => val.to_string(),
+                ControlValue::Boolean(val) => val.to_string(),
+                _ => continue,
+            },
+            DeviceControls::Button(_) => continue,
+        };
+        lines.push(format!("{} (0x{:08x}) = {}", control.name(), control.id(), value));
+    }
+}
+
+pub fn write_png(filename: &Path, frame: &Frame, metadata: &[(String, String)]) -> Result<(), String> {
+    let file = File::create(filename).map_err(|e| format!("{}", e))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), frame.width, frame.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    // International text chunks take UTF-8, unlike plain ones limited to Latin-1.
+    for (key, value) in metadata {
+        encoder.add_itxt_chunk(key.clone(), value.clone()).map_err(|e| format!("{}", e))?;
+    }
+    let mut writer = encoder.write_header().map_err(|e| format!("{}", e))?;
+    writer.write_image_data(&frame.pixels).map_err(|e| format!("{}", e))
+}
+
+/// JPEG has no key/value metadata without EXIF, so the values go in a comment segment.
+fn write_jpeg(filename: &Path, frame: &Frame, metadata: &[(String, String)]) -> Result<(), String> {
+    let rgb: Vec<u8> = frame.pixels.chunks_exact(4).flat_map(|px| [px[0], px[1], px[2]]).collect();
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY)
+        .encode(&rgb, frame.width, frame.height, ColorType::Rgb8)
+        .map_err(|e| format!("{}", e))?;
+
+    let comment: String = metadata.iter()
+        .map(|(key, value)| format!("{}: {}\n", key, value))
+        .collect();
+    // A segment length is 16 bits and counts its own two bytes.
+    let comment = &comment.as_bytes()[..comment.len().min(u16::MAX as usize - 2)];
+    let length = (comment.len() + 2) as u16;
+    let mut segment = vec![0xff, 0xfe];
+    segment.extend_from_slice(&length.to_be_bytes());
+    segment.extend_from_slice(comment);
+    // After the JFIF header, which readers expect right after the start of image marker.
+    let at = match jpeg.get(2..6) {
+        Some([0xff, 0xe0, high, low]) => 4 + u16::from_be_bytes([*high, *low]) as usize,
+        _ => 2,
+    };
+    jpeg.splice(at..at, segment);
+
+    fs::write(filename, jpeg).map_err(|e| format!("{}", e))
+}
@@ -0,0 +1,141 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::collections::VecDeque;
+
+/// Frame intervals kept for the jitter graph.
+const HISTORY: usize = 120;
+/// Frames the measured rate is averaged over.
+const RATE_WINDOW: usize = 30;
+const GRAPH_HEIGHT: u32 = 64;
+/// Pixels per interval in the graph.
+const BAR_WIDTH: u32 = 2;
+
+/// How a stream has been doing since it started.
+#[derive(Debug, Clone, Default)]
+pub struct StreamStats {
+    /// Measured over the last frames.
+    pub fps: f32,
+    /// From the negotiated frame interval.
+    pub nominal_fps: f32,
+    pub frames: u64,
+    /// Frames the driver numbered but never handed over, from gaps in the sequence.
+    pub dropped: u64,
+    /// Frames that arrived but couldn't be decoded.
+    pub skipped: u64,
+    /// Frames the app took longer than a frame interval to take.
+    pub late: u64,
+    /// Standard deviation of the frame interval, in milliseconds.
+    pub jitter: f32,
+    /// Time between frames, oldest first, in milliseconds.
+    pub intervals: Vec<f32>,
+}
+
+/// Builds `StreamStats` from each frame's sequence number and timestamp.
+#[derive(Debug)]
+pub struct StatsCollector {
+    nominal_fps: f32,
+    frames: u64,
+    dropped: u64,
+    skipped: u64,
+    late: u64,
+    last_sequence: Option<u32>,
+    last_time: Option<f64>,
+    intervals: VecDeque<f32>,
+}
+
+impl StatsCollector {
+    pub fn new(nominal_fps: f32) -> Self {
+        Self {
+            nominal_fps,
+            frames: 0,
+            dropped: 0,
+            skipped: 0,
+            late: 0,
+            last_sequence: None,
+            last_time: None,
+            intervals: VecDeque::with_capacity(HISTORY),
+        }
+    }
+
+    /// Counts a frame, `time` being its capture time in seconds.
+    pub fn frame(&mut self, sequence: u32, time: f64) {
+        self.frames += 1;
+        if let Some(last) = self.last_sequence {
+            self.dropped += sequence.wrapping_sub(last).saturating_sub(1) as u64;
+        }
+        if let Some(last) = self.last_time {
+            if self.intervals.len() == HISTORY {
+                self.intervals.pop_front();
+            }
+            self.intervals.push_back(((time - last) * 1000.0) as f32);
+        }
+        self.last_sequence = Some(sequence);
+        self.last_time = Some(time);
+    }
+
+    pub fn skipped(&mut self) {
+        self.skipped += 1;
+    }
+
+    pub fn late(&mut self) {
+        self.late += 1;
+    }
+
+    /// The nominal time between frames.
+    pub fn frame_interval(&self) -> f32 {
+        1000.0 / self.nominal_fps.max(1.0)
+    }
+
+    pub fn stats(&self) -> StreamStats {
+        let recent: Vec<f32> = self.intervals.iter().rev().take(RATE_WINDOW).copied().collect();
+        let mean = |values: &[f32]| values.iter().sum::<f32>() / values.len().max(1) as f32;
+        let recent_mean = mean(&recent);
+        let intervals: Vec<f32> = self.intervals.iter().copied().collect();
+        let all_mean = mean(&intervals);
+        let variance = intervals.iter().map(|interval| (interval - all_mean).powi(2)).sum::<f32>()
+            / intervals.len().max(1) as f32;
+        StreamStats {
+            fps: if recent_mean > 0.0 { 1000.0 / recent_mean } else { 0.0 },
+            nominal_fps: self.nominal_fps,
+            frames: self.frames,
+            dropped: self.dropped,
+            skipped: self.skipped,
+            late: self.late,
+            jitter: variance.sqrt(),
+            intervals,
+        }
+    }
+}
+
+impl StreamStats {
+    /// Draws the frame intervals as bars, with a line at the nominal interval
+    /// and bars over one and a half intervals in red. Returns RGBA pixels.
+    pub fn graph(&self) -> (u32, u32, Vec<u8>) {
+        let (width, height) = (HISTORY as u32 * BAR_WIDTH, GRAPH_HEIGHT);
+        let mut pixels = [16u8, 16, 16, 255].repeat((width * height) as usize);
+        let mut set = |x: u32, y: u32, color: [u8; 4]| {
+            if x < width && y < height {
+                let i = ((y * width + x) * 4) as usize;
+                pixels[i..i + 4].copy_from_slice(&color);
+            }
+        };
+        let nominal = 1000.0 / self.nominal_fps.max(1.0);
+        // Room for three intervals, so a single dropped frame stands out.
+        let scale = (height - 1) as f32 / (nominal * 3.0);
+        let level = |ms: f32| height - 1 - ((ms * scale) as u32).min(height - 1);
+
+        let offset = HISTORY.saturating_sub(self.intervals.len()) as u32 * BAR_WIDTH;
+        for (i, interval) in self.intervals.iter().enumerate() {
+            let color = if *interval > nominal * 1.5 { [230, 70, 60, 255] } else { [120, 200, 255, 255] };
+            for x in 0..BAR_WIDTH {
+                for y in level(*interval)..height {
+                    set(offset + i as u32 * BAR_WIDTH + x, y, color);
+                }
+            }
+        }
+        for x in 0..width {
+            set(x, level(nominal), [230, 180, 40, 255]);
+        }
+        (width, height, pixels)
+    }
+}
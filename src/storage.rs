@@ -1,31 +1,216 @@
 use std::fs::File;
-use std::io::Write;
+use std::io::{BufRead, BufReader, Write};
 use xdg::BaseDirectories;
 use std::collections::HashMap;
 
+use crate::app::MenuAction;
+use crate::auto::AutoSettings;
 use crate::device::VideoDevice;
+use crate::loopback::Transform;
+use crate::ptz::Preset;
+use crate::overlays::Guides;
+use crate::settings::Config;
 
-#[derive(Debug)]
+pub const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Debug, Clone, Default)]
 pub struct SaveData {
-    pub controls: HashMap<u32, u32>,
-    // TODO: Profile name.
+    pub controls: HashMap<u32, i64>,
+    /// Software transform for the virtual camera.
+    pub transform: Option<Transform>,
     // Capture stuff - resolution, format, etc.
 }
 
-pub fn get_save_filename(device: &VideoDevice) -> String {
+pub fn get_device_id(device: &VideoDevice) -> String {
     let device_name = device.name.replace(' ', "_");
     let usb_bus = device.capabilities.bus.clone();
-    format!("{}-{}.cfg", device_name, usb_bus)
+    format!("{}-{}", device_name, usb_bus)
+}
+
+pub fn get_save_filename(device: &VideoDevice) -> String {
+    format!("{}.cfg", get_device_id(device))
+}
+
+pub fn get_profile_filename(device: &VideoDevice, profile: &str) -> String {
+    if profile == DEFAULT_PROFILE {
+        return get_save_filename(device);
+    }
+    format!("{}@{}.cfg", get_device_id(device), profile.replace(['/', ' '], "_"))
+}
+
+pub fn get_favorites_filename(device: &VideoDevice) -> String {
+    format!("{}.favorites", get_device_id(device))
+}
+
+/// Lists the saved profiles of a device, with the default profile first.
+pub fn get_profiles(device: &VideoDevice) -> Vec<String> {
+    let mut profiles = vec![DEFAULT_PROFILE.to_string()];
+    let xdg_dirs = BaseDirectories::with_prefix("cosmic-cam").unwrap();
+    let prefix = format!("{}@", get_device_id(device));
+    let mut named: Vec<String> = xdg_dirs.list_data_files("")
+        .iter()
+        .filter_map(|path| path.file_name()?.to_str().map(String::from))
+        .filter_map(|name| {
+            name.strip_prefix(&prefix)?.strip_suffix(".cfg").map(String::from)
+        })
+        .collect();
+    named.sort();
+    profiles.append(&mut named);
+    profiles
 }
 
 pub async fn save_device_state(filename: String, save_data: SaveData) -> std::io::Result<()> {
     let xdg_dirs = BaseDirectories::with_prefix("cosmic-cam").unwrap();
     let data_path = xdg_dirs.place_data_file(filename).unwrap();
-    
+
     // TODO: Serialize the save data.
     let mut file = File::create(data_path)?;
     for (key, value) in save_data.controls.iter() {
         file.write_fmt(format_args!("{}={}\n", key, value))?;
     }
+    if let Some(transform) = save_data.transform {
+        file.write_fmt(format_args!("transform={}\n", transform.to_config()))?;
+    }
+    Ok(())
+}
+
+pub async fn load_device_state(filename: String) -> Result<SaveData, String> {
+    let xdg_dirs = BaseDirectories::with_prefix("cosmic-cam").unwrap();
+    let data_path = xdg_dirs
+        .find_data_file(&filename)
+        .ok_or(format!("No saved state: {}", filename))?;
+
+    let file = File::open(data_path).map_err(|e| format!("{}", e))?;
+    let mut save_data = SaveData::default();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| format!("{}", e))?;
+        if let Some((key, value)) = line.split_once('=') {
+            if key == "transform" {
+                save_data.transform = Some(Transform::from_config(value));
+            } else if let (Ok(key), Ok(value)) = (key.parse(), value.parse()) {
+                save_data.controls.insert(key, value);
+            }
+        }
+    }
+    Ok(save_data)
+}
+
+pub async fn save_favorites(filename: String, favorites: Vec<u32>) -> std::io::Result<()> {
+    let xdg_dirs = BaseDirectories::with_prefix("cosmic-cam").unwrap();
+    let data_path = xdg_dirs.place_data_file(filename).unwrap();
+
+    let mut file = File::create(data_path)?;
+    for id in favorites.iter() {
+        file.write_fmt(format_args!("favorite={}\n", id))?;
+    }
+    Ok(())
+}
+
+pub fn get_presets_filename(device: &VideoDevice) -> String {
+    format!("{}.presets", get_device_id(device))
+}
+
+pub async fn save_presets(filename: String, presets: Vec<Preset>) -> std::io::Result<()> {
+    let xdg_dirs = BaseDirectories::with_prefix("cosmic-cam").unwrap();
+    let data_path = xdg_dirs.place_data_file(filename).unwrap();
+
+    let mut file = File::create(data_path)?;
+    for preset in presets.iter() {
+        file.write_fmt(format_args!("[{}]\n", preset.name))?;
+        for (id, value) in preset.values.iter() {
+            file.write_fmt(format_args!("{}={}\n", id, value))?;
+        }
+    }
+    Ok(())
+}
+
+/// Loads the position presets of a device: a `[name]` line, then its control values.
+pub async fn load_presets(filename: String) -> Vec<Preset> {
+    let xdg_dirs = BaseDirectories::with_prefix("cosmic-cam").unwrap();
+    let Some(file) = xdg_dirs.find_data_file(&filename).and_then(|p| File::open(p).ok()) else {
+        return Vec::new();
+    };
+
+    let mut presets: Vec<Preset> = Vec::new();
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            presets.push(Preset { name: name.to_string(), values: Vec::new() });
+        } else if let (Some(preset), Some((id, value))) = (presets.last_mut(), line.split_once('=')) {
+            if let (Ok(id), Ok(value)) = (id.parse(), value.parse()) {
+                preset.values.push((id, value));
+            }
+        }
+    }
+    presets
+}
+
+/// Loads the pinned control ids of a device, in the order they were pinned.
+pub async fn load_favorites(filename: String) -> Vec<u32> {
+    let xdg_dirs = BaseDirectories::with_prefix("cosmic-cam").unwrap();
+    let Some(file) = xdg_dirs.find_data_file(&filename).and_then(|p| File::open(p).ok()) else {
+        return Vec::new();
+    };
+
+    BufReader::new(file).lines()
+        .map_while(Result::ok)
+        .filter_map(|line| line.strip_prefix("favorite=")?.parse().ok())
+        .collect()
+}
+
+pub fn load_config() -> Config {
+    let mut config = Config::default();
+    let xdg_dirs = BaseDirectories::with_prefix("cosmic-cam").unwrap();
+    let Some(file) = xdg_dirs.find_config_file("settings.cfg").and_then(|p| File::open(p).ok()) else {
+        return config;
+    };
+
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if let Some(action) = key.strip_prefix("keybind.").and_then(MenuAction::from_id) {
+            config.key_binds.insert(action, value.to_string());
+        } else if key == "poll_interval" {
+            config.poll_interval = value.parse().unwrap_or(config.poll_interval);
+        } else if key == "preset_transition" {
+            config.preset_transition = value.parse().unwrap_or(config.preset_transition);
+        } else if key == "scope_interval" {
+            config.scope_interval = value.parse().unwrap_or(config.scope_interval);
+        } else if let Some(device) = key.strip_prefix("fov.") {
+            if let Ok(fov) = value.parse() {
+                config.fov.insert(device.to_string(), fov);
+            }
+        } else if let Some(device) = key.strip_prefix("auto.") {
+            config.auto.insert(device.to_string(), AutoSettings::from_config(value));
+        } else if let Some(device) = key.strip_prefix("guides.") {
+            config.guides.insert(device.to_string(), Guides::from_config(value));
+        } else if key == "recording_folder" {
+            config.recording_folder = value.to_string();
+        }
+    }
+    config
+}
+
+pub async fn save_config(config: Config) -> std::io::Result<()> {
+    let xdg_dirs = BaseDirectories::with_prefix("cosmic-cam").unwrap();
+    let config_path = xdg_dirs.place_config_file("settings.cfg").unwrap();
+
+    let mut file = File::create(config_path)?;
+    file.write_fmt(format_args!("poll_interval={}\n", config.poll_interval))?;
+    file.write_fmt(format_args!("preset_transition={}\n", config.preset_transition))?;
+    file.write_fmt(format_args!("scope_interval={}\n", config.scope_interval))?;
+    file.write_fmt(format_args!("recording_folder={}\n", config.recording_folder))?;
+    for (device, fov) in config.fov.iter() {
+        file.write_fmt(format_args!("fov.{}={}\n", device, fov))?;
+    }
+    for (device, auto) in config.auto.iter() {
+        file.write_fmt(format_args!("auto.{}={}\n", device, auto.to_config()))?;
+    }
+    for (device, guides) in config.guides.iter() {
+        file.write_fmt(format_args!("guides.{}={}\n", device, guides.to_config()))?;
+    }
+    for (action, shortcut) in config.key_binds.iter() {
+        file.write_fmt(format_args!("keybind.{}={}\n", action.id(), shortcut))?;
+    }
     Ok(())
 }
@@ -0,0 +1,406 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::time::{Duration, Instant};
+
+use cosmic::iced::futures::{SinkExt, Stream};
+use cosmic::iced::{stream, Subscription};
+use tokio::sync::{mpsc, watch};
+use v4l::buffer::Type;
+use v4l::framesize::FrameSizeEnum;
+use v4l::io::traits::CaptureStream;
+use v4l::prelude::*;
+use v4l::video::Capture;
+use v4l::{Format, FourCC};
+
+use crate::loopback::{Output, Transform};
+use crate::record::{Recorder, Recording, RecordingStats};
+use crate::stats::{StatsCollector, StreamStats};
+
+const BUFFER_COUNT: u32 = 4;
+const SETTLE_FRAMES: usize = 3;
+const STATS_INTERVAL: Duration = Duration::from_millis(500);
+const BUSY_RETRY: Duration = Duration::from_millis(50);
+const BUSY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A decoded camera frame.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA pixels, row by row.
+    pub pixels: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Frame(Frame),
+    Recording(RecordingStats),
+    Stats(StreamStats),
+    RecordingError(String),
+    /// Writing to the virtual camera failed; the stream itself goes on.
+    OutputError(String),
+    Error(String),
+}
+
+/// Where the stream goes besides the preview.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sinks {
+    pub recording: Option<Recording>,
+    /// The loopback node to feed as a virtual camera.
+    pub output: Option<String>,
+}
+
+/// Streams decoded frames from the device at `path` for as long as the
+/// subscription is alive, also writing them to the recording and virtual
+/// camera in `sinks`. The running stream follows changes to `sinks` and
+/// `transform`, since restarting it while the old capture still holds the
+/// device's buffers would fail.
+pub fn frames(path: String, sinks: watch::Receiver<Sinks>, transform: watch::Receiver<Transform>) -> Subscription<Event> {
+    Subscription::run_with_id(path.clone(), capture_stream(path, sinks, transform))
+}
+
+fn capture_stream(
+    path: String,
+    sinks: watch::Receiver<Sinks>,
+    transform: watch::Receiver<Transform>,
+) -> impl Stream<Item = Event> {
+    stream::channel(1, move |mut output| async move {
+        let (sender, mut receiver) = mpsc::channel(1);
+
+        // The capture loop blocks on the device, so it gets its own thread.
+        // It stops once the subscription is dropped and the receiver with it.
+        std::thread::spawn(move || {
+            if let Err(err) = capture(&path, sinks, &transform, &sender) {
+                let _ = sender.blocking_send(Event::Error(err));
+            }
+        });
+
+        while let Some(event) = receiver.recv().await {
+            if output.send(event).await.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+fn capture(
+    path: &str,
+    sinks: watch::Receiver<Sinks>,
+    transform: &watch::Receiver<Transform>,
+    sender: &mpsc::Sender<Event>,
+) -> Result<(), String> {
+    let dev = Device::with_path(path).map_err(|e| format!("{}", e))?;
+    let format = get_stream_format(&dev)?;
+    let mut targets = Targets { sinks, applied: Sinks::default(), recorder: None, output: None };
+    let result = capture_frames(&dev, &format, &mut targets, transform, sender);
+    // Runs however the stream ended, so recordings are always left playable.
+    targets.finish_recording();
+    result
+}
+
+/// The recording and virtual camera the stream is writing to, kept in line
+/// with the wanted `sinks`.
+struct Targets {
+    sinks: watch::Receiver<Sinks>,
+    applied: Sinks,
+    recorder: Option<Recorder>,
+    /// Opened on the first frame after the virtual camera is turned on, once
+    /// the decoded size is known.
+    output: Option<Output>,
+}
+
+impl Targets {
+    /// Starts or finishes the recording and switches the virtual camera when
+    /// the wanted sinks changed.
+    fn update(&mut self, dev: &Device, format: &Format, sender: &mpsc::Sender<Event>) {
+        let wanted = self.sinks.borrow_and_update().clone();
+        if wanted.recording != self.applied.recording {
+            self.finish_recording();
+            if let Some(recording) = &wanted.recording {
+                match Recorder::new(recording, format, get_frame_rate(dev)) {
+                    Ok(recorder) => self.recorder = Some(recorder),
+                    Err(err) => {
+                        let _ = sender.blocking_send(Event::RecordingError(err));
+                    }
+                }
+            }
+        }
+        if wanted.output != self.applied.output {
+            self.output = None;
+        }
+        self.applied = wanted;
+    }
+
+    fn finish_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            if let Err(err) = recorder.finish() {
+                println!("Could not finish recording: {}", err);
+            }
+        }
+    }
+}
+
+fn capture_frames(
+    dev: &Device,
+    format: &Format,
+    targets: &mut Targets,
+    transform: &watch::Receiver<Transform>,
+    sender: &mpsc::Sender<Event>,
+) -> Result<(), String> {
+    let mut stream = open_stream(dev)?;
+    let mut last_stats = Instant::now();
+    let (rate, scale) = get_frame_rate(dev);
+    let mut collector = StatsCollector::new(rate as f32 / scale.max(1) as f32);
+    let mut last_stream_stats = Instant::now();
+    let started = Instant::now();
+    targets.update(dev, format, sender);
+
+    while !sender.is_closed() {
+        if targets.sinks.has_changed().unwrap_or(false) {
+            targets.update(dev, format, sender);
+        }
+        let (buf, meta) = stream.next().map_err(|e| format!("{}", e))?;
+        let data = match meta.bytesused as usize {
+            0 => buf,
+            used => &buf[..used.min(buf.len())],
+        };
+        // Drivers that leave the timestamp empty get the time we dequeued the frame.
+        let time = match (meta.timestamp.sec, meta.timestamp.usec) {
+            (0, 0) => started.elapsed().as_secs_f64(),
+            (sec, usec) => sec as f64 + usec as f64 / 1_000_000.0,
+        };
+        collector.frame(meta.sequence, time);
+        if last_stream_stats.elapsed() >= STATS_INTERVAL {
+            last_stream_stats = Instant::now();
+            let _ = sender.blocking_send(Event::Stats(collector.stats()));
+        }
+
+        if let Some(active) = &mut targets.recorder {
+            if let Err(err) = active.write(format, data) {
+                targets.recorder = None;
+                let _ = sender.blocking_send(Event::RecordingError(err));
+            } else if last_stats.elapsed() >= STATS_INTERVAL {
+                last_stats = Instant::now();
+                let _ = sender.blocking_send(Event::Recording(active.stats()));
+            }
+        }
+
+        let frame = match decode_frame(format, data) {
+            Ok(frame) => frame,
+            Err(err) => {
+                println!("Could not decode frame: {}", err);
+                collector.skipped();
+                continue;
+            }
+        };
+
+        if let Some(path) = &targets.applied.output {
+            let transform = *transform.borrow();
+            let transformed = transform.apply(&frame);
+            let written = match targets.output.as_mut() {
+                Some(output) => output.write(&transformed),
+                None => Output::open(path, frame.width, frame.height)
+                    .and_then(|opened| targets.output.insert(opened).write(&transformed)),
+            };
+            if let Err(err) = written {
+                // Stays off until the virtual camera is turned on again.
+                targets.applied.output = None;
+                targets.output = None;
+                let _ = sender.blocking_send(Event::OutputError(err));
+            }
+        }
+
+        let sending = Instant::now();
+        if sender.blocking_send(Event::Frame(frame)).is_err() {
+            break;
+        }
+        if sending.elapsed().as_secs_f32() * 1000.0 > collector.frame_interval() {
+            collector.late();
+        }
+    }
+    Ok(())
+}
+
+/// Maps the device's buffers. A stream that was just stopped may hold them
+/// until its capture thread sees its next frame, so busy devices get a moment.
+fn open_stream(dev: &Device) -> Result<MmapStream<'_>, String> {
+    let mut waited = Duration::ZERO;
+    loop {
+        match MmapStream::with_buffers(dev, Type::VideoCapture, BUFFER_COUNT) {
+            Ok(stream) => return Ok(stream),
+            Err(err) if err.kind() == std::io::ErrorKind::ResourceBusy && waited < BUSY_TIMEOUT => {
+                std::thread::sleep(BUSY_RETRY);
+                waited += BUSY_RETRY;
+            }
+            Err(err) => return Err(format!("{}", err)),
+        }
+    }
+}
+
+/// The configured frame rate as a rate and scale, falling back to 30 fps.
+fn get_frame_rate(dev: &Device) -> (u32, u32) {
+    match dev.params() {
+        Ok(params) if params.interval.numerator > 0 => {
+            (params.interval.denominator, params.interval.numerator)
+        }
+        _ => (30, 1),
+    }
+}
+
+/// Captures a single frame, optionally at another frame size, after which
+/// the device goes back to the format it had.
+pub fn grab_frame(path: &str, size: Option<(u32, u32)>) -> Result<Frame, String> {
+    let dev = Device::with_path(path).map_err(|e| format!("{}", e))?;
+    let Some((width, height)) = size else {
+        return capture_frame(&dev);
+    };
+    // Other apps expect the camera in the format they left it in.
+    let original = dev.format().map_err(|e| format!("{}", e))?;
+    let result = dev.set_format(&Format::new(width, height, original.fourcc))
+        .map_err(|e| format!("{}", e))
+        .and_then(|_| capture_frame(&dev));
+    if let Err(err) = dev.set_format(&original) {
+        println!("Could not restore the capture format: {}", err);
+    }
+    result
+}
+
+/// Grabs a frame in the device's current format. The first few frames are
+/// dropped while the sensor settles after starting.
+fn capture_frame(dev: &Device) -> Result<Frame, String> {
+    let format = get_stream_format(dev)?;
+    let mut stream = MmapStream::with_buffers(dev, Type::VideoCapture, BUFFER_COUNT)
+        .map_err(|e| format!("{}", e))?;
+
+    for _ in 0..SETTLE_FRAMES {
+        stream.next().map_err(|e| format!("{}", e))?;
+    }
+    let (buf, meta) = stream.next().map_err(|e| format!("{}", e))?;
+    let data = match meta.bytesused as usize {
+        0 => buf,
+        used => &buf[..used.min(buf.len())],
+    };
+    decode_frame(&format, data)
+}
+
+/// Calls `f` with each decoded frame from the device at `path` until it returns false.
+pub fn watch_frames(path: &str, mut f: impl FnMut(Frame) -> bool) -> Result<(), String> {
+    let dev = Device::with_path(path).map_err(|e| format!("{}", e))?;
+    let format = get_stream_format(&dev)?;
+    let mut stream = MmapStream::with_buffers(&dev, Type::VideoCapture, BUFFER_COUNT)
+        .map_err(|e| format!("{}", e))?;
+    loop {
+        let (buf, meta) = stream.next().map_err(|e| format!("{}", e))?;
+        let data = match meta.bytesused as usize {
+            0 => buf,
+            used => &buf[..used.min(buf.len())],
+        };
+        match decode_frame(&format, data) {
+            Ok(frame) => {
+                if !f(frame) {
+                    return Ok(());
+                }
+            }
+            Err(err) => println!("Could not decode frame: {}", err),
+        }
+    }
+}
+
+/// Lists the discrete frame sizes the device offers in its current pixel format.
+pub fn get_frame_sizes(path: &str) -> Result<Vec<(u32, u32)>, String> {
+    let dev = Device::with_path(path).map_err(|e| format!("{}", e))?;
+    let format = dev.format().map_err(|e| format!("{}", e))?;
+    let sizes = dev.enum_framesizes(format.fourcc).map_err(|e| format!("{}", e))?;
+    let mut sizes: Vec<(u32, u32)> = sizes.into_iter()
+        .filter_map(|size| match size.size {
+            FrameSizeEnum::Discrete(size) => Some((size.width, size.height)),
+            FrameSizeEnum::Stepwise(_) => None,
+        })
+        .collect();
+    sizes.sort_by_key(|(width, height)| std::cmp::Reverse(width * height));
+    sizes.dedup();
+    Ok(sizes)
+}
+
+pub async fn load_frame_sizes(path: String) -> Result<Vec<(u32, u32)>, String> {
+    crate::device::blocking(move || get_frame_sizes(&path)).await
+}
+
+/// Keeps the current format if we can decode it, otherwise asks the driver for YUYV.
+fn get_stream_format(dev: &Device) -> Result<Format, String> {
+    let format = dev.format().map_err(|e| format!("{}", e))?;
+    if is_supported(format.fourcc) {
+        return Ok(format);
+    }
+    let format = dev
+        .set_format(&Format::new(format.width, format.height, FourCC::new(b"YUYV")))
+        .map_err(|e| format!("{}", e))?;
+    if is_supported(format.fourcc) {
+        Ok(format)
+    } else {
+        Err(format!("Unsupported pixel format: {}", format.fourcc))
+    }
+}
+
+fn is_supported(fourcc: FourCC) -> bool {
+    matches!(&fourcc.repr, b"YUYV" | b"MJPG" | b"RGB3")
+}
+
+pub fn decode_frame(format: &Format, data: &[u8]) -> Result<Frame, String> {
+    let (width, height) = (format.width, format.height);
+    let pixels = match &format.fourcc.repr {
+        b"YUYV" => decode_yuyv(data, width, height, format.stride)?,
+        b"MJPG" => {
+            let image = image::load_from_memory_with_format(data, image::ImageFormat::Jpeg)
+                .map_err(|e| format!("{}", e))?
+                .to_rgba8();
+            return Ok(Frame {
+                width: image.width(),
+                height: image.height(),
+                pixels: image.into_raw(),
+            });
+        }
+        b"RGB3" => data
+            .chunks_exact(3)
+            .take((width * height) as usize)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect(),
+        _ => return Err(format!("Unsupported pixel format: {}", format.fourcc)),
+    };
+    Ok(Frame {
+        width,
+        height,
+        pixels,
+    })
+}
+
+fn decode_yuyv(data: &[u8], width: u32, height: u32, stride: u32) -> Result<Vec<u8>, String> {
+    let stride = if stride == 0 { width * 2 } else { stride } as usize;
+    if data.len() < stride * height as usize {
+        return Err(String::from("Short YUYV frame"));
+    }
+
+    let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+    for row in data.chunks_exact(stride).take(height as usize) {
+        for yuyv in row[..width as usize * 2].chunks_exact(4) {
+            let (y0, u, y1, v) = (yuyv[0], yuyv[1], yuyv[2], yuyv[3]);
+            pixels.extend_from_slice(&yuv_to_rgba(y0, u, v));
+            pixels.extend_from_slice(&yuv_to_rgba(y1, u, v));
+        }
+    }
+    Ok(pixels)
+}
+
+/// BT.601 limited range conversion.
+fn yuv_to_rgba(y: u8, u: u8, v: u8) -> [u8; 4] {
+    let c = (y as i32 - 16) * 298;
+    let d = u as i32 - 128;
+    let e = v as i32 - 128;
+    let clamp = |x: i32| ((x + 128) >> 8).clamp(0, 255) as u8;
+    [
+        clamp(c + 409 * e),
+        clamp(c - 100 * d - 208 * e),
+        clamp(c + 516 * d),
+        255,
+    ]
+}
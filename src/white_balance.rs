@@ -0,0 +1,242 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::time::{Duration, Instant};
+
+use crate::cid;
+use crate::device::{find_control, ControlData, DeviceControls, IntegerControl};
+use crate::stream::Frame;
+
+/// Time for a written balance to show up in the frames.
+const SETTLE: Duration = Duration::from_millis(600);
+/// How far from neutral a channel may stay, as the log of its ratio to the reference.
+const TOLERANCE: f64 = 0.015;
+const MAX_ROUNDS: u32 = 14;
+/// Half the side of the sampled square, as a share of the frame width.
+const SAMPLE_RADIUS: f32 = 0.015;
+
+/// Average red, green and blue in a small square around a point given in
+/// fractions of the frame, `None` if the point is off the frame.
+pub fn sample(frame: &Frame, (x, y): (f32, f32)) -> Option<[f64; 3]> {
+    if !(0.0..1.0).contains(&x) || !(0.0..1.0).contains(&y) {
+        return None;
+    }
+    let (cx, cy) = ((x * frame.width as f32) as u32, (y * frame.height as f32) as u32);
+    let radius = ((frame.width as f32 * SAMPLE_RADIUS) as u32).max(2);
+    let mut sum = [0.0; 3];
+    let mut count = 0.0;
+    for py in cy.saturating_sub(radius)..(cy + radius).min(frame.height) {
+        for px in cx.saturating_sub(radius)..(cx + radius).min(frame.width) {
+            let i = ((py * frame.width + px) * 4) as usize;
+            if let Some(pixel) = frame.pixels.get(i..i + 3) {
+                sum.iter_mut().zip(pixel).for_each(|(sum, c)| *sum += *c as f64);
+                count += 1.0;
+            }
+        }
+    }
+    (count > 0.0).then(|| sum.map(|sum| sum / count))
+}
+
+/// How a control is judged against the sampled color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Channel {
+    /// Too low a temperature leaves the picture blue.
+    Temperature,
+    Red,
+    Blue,
+}
+
+impl Channel {
+    /// Positive when the control is set too high, in log ratio.
+    fn excess(&self, [r, g, b]: [f64; 3]) -> f64 {
+        let ratio = |a: f64, b: f64| (a.max(1.0) / b.max(1.0)).ln();
+        match self {
+            Channel::Temperature => ratio(r, b),
+            Channel::Red => ratio(r, g),
+            Channel::Blue => ratio(b, g),
+        }
+    }
+}
+
+/// Bisects a control's range toward the value that neutralizes its channel.
+#[derive(Debug, Clone)]
+struct Search {
+    channel: Channel,
+    id: u32,
+    min: i64,
+    step: i64,
+    low: i64,
+    high: i64,
+    value: i64,
+    excess: f64,
+    done: bool,
+}
+
+impl Search {
+    fn new(channel: Channel, control: &IntegerControl, current: i64) -> Self {
+        Self {
+            channel,
+            id: control.id,
+            min: control.min,
+            step: control.step.max(1) as i64,
+            low: control.min,
+            high: control.max,
+            value: current,
+            excess: 0.0,
+            done: false,
+        }
+    }
+
+    /// Narrows the range from the color at the current value; returns the
+    /// next value to try, `None` once neutral or out of range to narrow.
+    fn update(&mut self, color: [f64; 3]) -> Option<i64> {
+        self.excess = self.channel.excess(color);
+        if self.excess.abs() < TOLERANCE {
+            self.done = true;
+            return None;
+        }
+        if self.excess > 0.0 {
+            self.high = self.value;
+        } else {
+            self.low = self.value;
+        }
+        let middle = self.low + (self.high - self.low) / 2;
+        let next = self.min + (middle - self.min) / self.step * self.step;
+        if self.high - self.low <= self.step || next == self.value {
+            self.done = true;
+            return None;
+        }
+        self.value = next;
+        Some(next)
+    }
+}
+
+/// What to do after a frame while neutralizing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BalanceStep {
+    Wait,
+    Write(Vec<(u32, i64)>),
+    /// Settled, with whether the sample came out neutral.
+    Done(bool),
+}
+
+/// The final balance, for reporting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Balance {
+    Temperature(i64),
+    RedBlue(i64, i64),
+}
+
+/// Adjusts white balance, a write at a time, until a point in the picture is neutral.
+#[derive(Debug, Clone)]
+pub struct Neutralize {
+    point: (f32, f32),
+    searches: Vec<Search>,
+    rounds: u32,
+    until: Instant,
+}
+
+impl Neutralize {
+    /// Uses red and blue balance where the device has both, otherwise the
+    /// temperature; `None` if it has neither. `current` gives the shown value.
+    pub fn new(controls: &[DeviceControls], point: (f32, f32), current: impl Fn(&IntegerControl) -> i64) -> Option<Self> {
+        let integer = |id| match find_control(controls, id) {
+            Some(DeviceControls::Integer(control)) if !control.flags().contains(v4l::control::Flags::READ_ONLY) => Some(control),
+            _ => None,
+        };
+        let searches = match (integer(cid::RED_BALANCE), integer(cid::BLUE_BALANCE), integer(cid::WHITE_BALANCE_TEMPERATURE)) {
+            (Some(red), Some(blue), _) => vec![
+                Search::new(Channel::Red, red, current(red)),
+                Search::new(Channel::Blue, blue, current(blue)),
+            ],
+            (_, _, Some(temperature)) => vec![Search::new(Channel::Temperature, temperature, current(temperature))],
+            _ => return None,
+        };
+        Some(Self { point, searches, rounds: 0, until: Instant::now() + SETTLE })
+    }
+
+    /// Samples the point once the last write has settled and says what to write next.
+    pub fn frame(&mut self, frame: &Frame) -> BalanceStep {
+        let now = Instant::now();
+        if now < self.until {
+            return BalanceStep::Wait;
+        }
+        let Some(color) = sample(frame, self.point) else {
+            return BalanceStep::Done(false);
+        };
+        if self.rounds >= MAX_ROUNDS {
+            return BalanceStep::Done(self.searches.iter().all(|search| search.channel.excess(color).abs() < TOLERANCE));
+        }
+        self.rounds += 1;
+        let writes: Vec<(u32, i64)> = self.searches.iter_mut()
+            .filter(|search| !search.done)
+            .filter_map(|search| Some((search.id, search.update(color)?)))
+            .collect();
+        if writes.is_empty() {
+            return BalanceStep::Done(self.searches.iter().all(|search| search.excess.abs() < TOLERANCE));
+        }
+        self.until = now + SETTLE;
+        BalanceStep::Write(writes)
+    }
+
+    pub fn balance(&self) -> Balance {
+        let value = |channel| self.searches.iter().find(|search| search.channel == channel).map_or(0, |search| search.value);
+        match self.searches.iter().any(|search| search.channel == Channel::Temperature) {
+            true => Balance::Temperature(value(Channel::Temperature)),
+            false => Balance::RedBlue(value(Channel::Red), value(Channel::Blue)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temperature(min: i64, max: i64) -> IntegerControl {
+        IntegerControl::fixture(cid::WHITE_BALANCE_TEMPERATURE, min, max, 1)
+    }
+
+    /// A camera under 5000 K light: a lower setting leaves the picture blue.
+    fn color_at(temperature: i64) -> [f64; 3] {
+        let shift = temperature as f64 / 5000.0;
+        [128.0 * shift, 128.0, 128.0 / shift]
+    }
+
+    #[test]
+    fn excess_is_positive_when_set_too_high() {
+        let bluish = [100.0, 128.0, 160.0];
+        let reddish = [160.0, 128.0, 100.0];
+        assert!(Channel::Temperature.excess(bluish) < 0.0);
+        assert!(Channel::Temperature.excess(reddish) > 0.0);
+        assert!(Channel::Red.excess(reddish) > 0.0);
+        assert!(Channel::Blue.excess(bluish) > 0.0);
+        assert!(Channel::Red.excess([128.0; 3]).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn a_bluish_sample_raises_the_temperature() {
+        let mut search = Search::new(Channel::Temperature, &temperature(2800, 6500), 4000);
+        let next = search.update(color_at(4000)).unwrap();
+        assert!(next > 4000);
+    }
+
+    #[test]
+    fn search_settles_on_the_neutral_temperature() {
+        let mut search = Search::new(Channel::Temperature, &temperature(2800, 6500), 3000);
+        let mut value = 3000;
+        for _ in 0..MAX_ROUNDS {
+            match search.update(color_at(value)) {
+                Some(next) => value = next,
+                None => break,
+            }
+        }
+        assert!(search.done);
+        assert!((value - 5000).abs() < 50, "{value}");
+    }
+
+    #[test]
+    fn sample_averages_around_the_point() {
+        let frame = Frame { width: 100, height: 100, pixels: [10, 20, 30, 255].repeat(100 * 100) };
+        assert_eq!(sample(&frame, (0.5, 0.5)), Some([10.0, 20.0, 30.0]));
+        assert_eq!(sample(&frame, (1.5, 0.5)), None);
+    }
+}
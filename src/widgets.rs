@@ -1,7 +1,8 @@
 use std::{borrow::Cow, rc::Rc};
 
-use cosmic::{iced::Background, widget::{self, slider, Slider}, Element};
-use crate::content::Message;
+use cosmic::{iced::{Alignment, Background, Length}, widget::{self, slider, Slider}, Element};
+use crate::content::{Message, Nudge};
+use crate::fl;
 use cosmic::theme::iced::Slider as SliderTheme;
 use cosmic::theme::Theme;
 
@@ -22,6 +23,75 @@ pub fn reset_button<'a>(
         .into()
 }
 
+/// Pins a control to the favorites section, or unpins it.
+pub fn pin_button<'a>(id: u32, pinned: bool) -> Element<'a, Message> {
+    let (icon, tooltip) = if pinned {
+        ("starred-symbolic", fl!("unpin-control"))
+    } else {
+        ("non-starred-symbolic", fl!("pin-control"))
+    };
+    widget::button::icon(widget::icon::from_name(icon))
+        .on_press(Message::ToggleFavorite(id))
+        .extra_small()
+        .tooltip(tooltip)
+        .padding(0.0)
+        .into()
+}
+
+/// Marks a control whose value is being polled from the hardware.
+pub fn live_marker<'a>(live: bool) -> Element<'a, Message> {
+    if !live {
+        return widget::Space::with_width(Length::Shrink).into();
+    }
+    widget::tooltip(
+        widget::text::caption(fl!("live")),
+        widget::text::text(fl!("live-tooltip")),
+        widget::tooltip::Position::Top,
+    ).into()
+}
+
+/// Shown next to a control while a write to the device is still running.
+pub fn busy_indicator<'a>(busy: bool) -> Element<'a, Message> {
+    if !busy {
+        return widget::Space::with_width(Length::Fixed(16.0)).into();
+    }
+    widget::icon::from_name("process-working-symbolic")
+        .size(16)
+        .icon()
+        .into()
+}
+
+/// A spin box style entry: the exact value in a text field between step buttons.
+pub fn integer_entry<'a>(id: u32, text: String, disabled: bool) -> Element<'a, Message> {
+    let button = |icon: &'static str, nudge: Nudge| {
+        let button = widget::button::icon(widget::icon::from_name(icon))
+            .extra_small()
+            .padding(0.0);
+        if disabled {
+            button
+        } else {
+            button.on_press(Message::Nudge(id, nudge))
+        }
+    };
+    let input = widget::text_input("", text).width(Length::Fixed(120.0));
+    let input = if disabled {
+        input
+    } else {
+        input
+            .on_input(move |val| Message::IntegerInput(id, val))
+            .on_submit(Message::IntegerSubmit(id))
+            .on_focus(Message::EntryFocus(id, true))
+            .on_unfocus(Message::EntryFocus(id, false))
+    };
+
+    widget::row()
+        .align_y(Alignment::Center)
+        .push(button("list-remove-symbolic", Nudge::Down))
+        .push(input)
+        .push(button("list-add-symbolic", Nudge::Up))
+        .into()
+}
+
 pub fn custom_slider<'a, T, Message>(min: T, max: T, val: T, step: T, disabled: bool, on_change: impl Fn(T) -> Message + 'a ) -> Slider<'a, T, Message, Theme>
 where
     T: Copy + From<u8> + std::cmp::PartialOrd,
@@ -31,7 +101,7 @@ where
         min..=max,
         val,
         on_change
-    );
+    ).step(step);
     if !disabled {
         return slider
     }